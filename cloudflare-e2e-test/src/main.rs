@@ -5,7 +5,8 @@ mod storage_databases;
 use clap::{Arg, Command};
 use cloudflare::framework::client::async_api::Client as AsyncClient;
 use cloudflare::framework::client::ClientConfig;
-use cloudflare::framework::{auth::Credentials, client::async_api, Environment};
+use cloudflare::framework::auth::{ChainProvider, Credentials, CredentialsProvider};
+use cloudflare::framework::{client::async_api, Environment};
 use std::fmt::Display;
 
 async fn tests(api_client: &AsyncClient, account_id: &str) -> anyhow::Result<()> {
@@ -48,19 +49,26 @@ async fn main() -> anyhow::Result<()> {
         .arg_required_else_help(true);
 
     let mut matches = cli.get_matches();
-    let email = matches.remove_one("email");
-    let key = matches.remove_one("auth-key");
-    let token = matches.remove_one("auth-token");
+    let email = matches.remove_one::<String>("email");
+    let key = matches.remove_one::<String>("auth-key");
+    let token = matches.remove_one::<String>("auth-token");
     let account_id: String = matches
         .remove_one("account-id")
         .expect("account_id is mandatory");
 
-    let credentials: Credentials = if let (Some(email), Some(key)) = (email, key) {
-        Credentials::UserAuthKey { email, key }
+    // Without credentials on the command line, they are read from the environment or a profile.
+    let credentials: Box<dyn CredentialsProvider> = if let (Some(email), Some(key)) = (email, key)
+    {
+        Box::new(Credentials::UserAuthKey {
+            email,
+            key: key.into(),
+        })
     } else if let Some(token) = token {
-        Credentials::UserAuthToken { token }
+        Box::new(Credentials::UserAuthToken {
+            token: token.into(),
+        })
     } else {
-        panic!("Either API token or API key + email pair must be provided")
+        Box::new(ChainProvider::from_env())
     };
 
     let api_client = async_api::Client::new(
@@ -405,12 +405,12 @@ pub async fn test_kv(client: &AsyncClient, account_id: &str) -> anyhow::Result<(
     .await
     .expect_err("Error while checking error handling");
     match result {
-        ApiFailure::Error(status, errors) => {
+        ApiFailure::Error(status, errors, _) => {
             assert_eq!(status, 404);
             assert_eq!(errors.errors.len(), 1);
             assert_eq!(errors.errors[0].code, 10013);
         }
-        ApiFailure::Invalid(e) => {
+        e => {
             panic!("Unexpected error: {e}");
         }
     }
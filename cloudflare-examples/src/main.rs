@@ -10,9 +10,9 @@ use cloudflare::framework::client::blocking_api::HttpApiClient;
 use cloudflare::framework::client::ClientConfig;
 use cloudflare::framework::endpoint::spec::EndpointSpec;
 use cloudflare::framework::response::{ApiErrors, ApiResult, ApiSuccess};
-use cloudflare::framework::response::{ResponseConverter, ResponseInfo};
+use cloudflare::framework::response::ResponseInfo;
 use cloudflare::framework::{
-    auth::Credentials,
+    auth::{ChainProvider, Credentials, CredentialsProvider},
     response::{ApiFailure, ApiResponse},
     Environment, OrderDirection,
 };
@@ -33,7 +33,7 @@ where
     match response {
         Ok(success) => println!("Success: {success:#?}"),
         Err(e) => match e {
-            ApiFailure::Error(status, errors) => {
+            ApiFailure::Error(status, errors, _) => {
                 println!("HTTP {status}:");
                 for err in errors.errors {
                     println!("Error {}: {}", err.code, err.message);
@@ -45,7 +45,7 @@ where
                     println!("{k}: {v}");
                 }
             }
-            ApiFailure::Invalid(reqwest_err) => println!("Error: {reqwest_err}"),
+            err => println!("Error: {err}"),
         },
     }
 }
@@ -58,7 +58,7 @@ where
     match response {
         Ok(success) => println!("{}", serde_json::to_string(&success.result).unwrap()),
         Err(e) => match e {
-            ApiFailure::Error(status, errors) => {
+            ApiFailure::Error(status, errors, _) => {
                 println!("HTTP {status}:");
                 for err in errors.errors {
                     println!("Error {}: {}", err.code, err.message);
@@ -70,7 +70,7 @@ where
                     println!("{k}: {v}");
                 }
             }
-            ApiFailure::Invalid(reqwest_err) => println!("Error: {reqwest_err}"),
+            err => println!("Error: {err}"),
         },
     }
 }
@@ -221,9 +221,6 @@ fn delete_route(arg_matches: &ArgMatches, api_client: &HttpApiClient) {
 fn add_static_mock<E>(endpoint: &E)
 where
     E: EndpointSpec,
-    // The endpoint's JSON response type isn't used for raw endpoints
-    // but we require that its final response type is ApiResult.
-    E::ResponseType: ResponseConverter<E::JsonResponse>,
 {
     let body = ApiErrors {
         errors: vec![ResponseInfo {
@@ -342,8 +339,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             .action(ArgAction::SetTrue))
         .group(ArgGroup::new("auth")
             .args(["email", "auth-key", "auth-token"])
-            .multiple(true)
-            .required(true))
+            .multiple(true))
         .arg_required_else_help(true);
 
     for (section_name, section) in sections.iter() {
@@ -370,15 +366,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             matches.subcommand_matches(section_name).is_some()
         });
 
-    let credentials: Credentials = if let Some(key) = key {
-        Credentials::UserAuthKey {
+    // Without credentials on the command line, they are read from the environment or a profile.
+    let credentials: Box<dyn CredentialsProvider> = if let Some(key) = key {
+        Box::new(Credentials::UserAuthKey {
             email: email.unwrap(),
-            key,
-        }
+            key: key.into(),
+        })
     } else if let Some(token) = token {
-        Credentials::UserAuthToken { token }
+        Box::new(Credentials::UserAuthToken {
+            token: token.into(),
+        })
     } else {
-        panic!("Either API token or API key + email pair must be provided")
+        Box::new(ChainProvider::from_env())
     };
 
     let api_client = HttpApiClient::new(credentials, ClientConfig::default(), environment)?;
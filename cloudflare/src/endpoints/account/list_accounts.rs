@@ -1,9 +1,9 @@
 use super::Account;
 
-use crate::framework::endpoint::{serialize_query, EndpointSpec, Method};
+use crate::framework::endpoint::{serialize_query, EndpointSpec, Method, Paginated};
 use crate::framework::OrderDirection;
 
-use crate::framework::response::ApiSuccess;
+use crate::framework::response::{ApiSuccess, ResultInfo};
 use serde::Serialize;
 
 /// List Accounts
@@ -30,6 +30,14 @@ impl EndpointSpec for ListAccounts {
     }
 }
 
+impl Paginated for ListAccounts {
+    fn next_page(&mut self, result_info: &ResultInfo) -> bool {
+        let params = self.params.get_or_insert_with(Default::default);
+        params.page = result_info.next_page();
+        params.page.is_some()
+    }
+}
+
 #[serde_with::skip_serializing_none]
 #[derive(Serialize, Clone, Debug, Default)]
 pub struct ListAccountsParams {
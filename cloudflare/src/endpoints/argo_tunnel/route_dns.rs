@@ -29,8 +29,7 @@ impl EndpointSpec for RouteTunnel<'_> {
     }
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        let body = serde_json::to_string(&self.params).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(&self.params))
     }
 }
 
@@ -2,8 +2,8 @@ use crate::endpoints::cfd_tunnel::Tunnel;
 use chrono::{DateTime, Utc};
 use serde::Serialize;
 
-use crate::framework::endpoint::{serialize_query, EndpointSpec, Method};
-use crate::framework::response::ApiSuccess;
+use crate::framework::endpoint::{serialize_query, EndpointSpec, Method, Paginated};
+use crate::framework::response::{ApiSuccess, ResultInfo};
 
 /// List/search tunnels in an account.
 /// <https://developers.cloudflare.com/api/operations/cloudflare-tunnel-list-cloudflare-tunnels>
@@ -29,6 +29,28 @@ impl EndpointSpec for ListTunnels<'_> {
     }
 }
 
+impl Paginated for ListTunnels<'_> {
+    fn next_page(&mut self, result_info: &ResultInfo) -> bool {
+        let per_page = match &self.params.pagination_params {
+            Some(pagination) => pagination.per_page,
+            None => match result_info.per_page {
+                Some(per_page) => per_page.into(),
+                None => return false,
+            },
+        };
+        match result_info.next_page() {
+            Some(page) => {
+                self.params.pagination_params = Some(PaginationParams {
+                    page: page.into(),
+                    per_page,
+                });
+                true
+            }
+            None => false,
+        }
+    }
+}
+
 /// Params for filtering listed tunnels
 #[serde_with::skip_serializing_none]
 #[derive(Serialize, Clone, Debug, Default)]
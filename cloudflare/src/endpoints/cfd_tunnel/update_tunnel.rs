@@ -8,6 +8,7 @@ use serde_with::{
 
 use crate::framework::endpoint::{EndpointSpec, Method, RequestBody};
 use crate::framework::response::ApiSuccess;
+use crate::framework::Secret;
 
 /// Create a Cfd Tunnel
 /// This creates the Tunnel, which can then be routed and ran. Creating the Tunnel per se is only
@@ -35,8 +36,7 @@ impl EndpointSpec for UpdateTunnel<'_> {
     }
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        let body = serde_json::to_string(&self.params).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(&self.params))
     }
 }
 
@@ -50,7 +50,7 @@ pub struct Params<'a> {
     /// The byte array (with 32 or more bytes) representing a secret for the tunnel. This is
     /// encoded into JSON as a base64 String. This secret is necessary to run the tunnel.
     #[serde_as(as = "Base64<Standard, Padded>")]
-    pub tunnel_secret: &'a Vec<u8>,
+    pub tunnel_secret: &'a Secret<Vec<u8>>,
 
     /// Arbitrary metadata for the tunnel.
     pub metadata: Option<serde_json::Value>,
@@ -1,6 +1,7 @@
 use crate::endpoints::custom_hostname::hostname::CustomHostnameDetails;
 use crate::framework::endpoint::{EndpointSpec, RequestBody};
 use crate::framework::response::{ApiResult, ApiSuccess};
+use crate::framework::Secret;
 use http::Method;
 use serde::{Deserialize, Serialize};
 
@@ -63,13 +64,12 @@ impl EndpointSpec for ReplaceCustomHostnameCertificate<'_> {
 
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        let body = serde_json::to_string(&self.params).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(&self.params))
     }
 }
 
 #[derive(Debug, Serialize, Clone)]
 pub struct ReplaceCustomHostnameCertificateParams {
     pub custom_certificate: String,
-    pub custom_key: String,
+    pub custom_key: Secret<String>,
 }
@@ -1,6 +1,6 @@
-use crate::framework::endpoint::{serialize_query, EndpointSpec, RequestBody};
-use crate::framework::response::{ApiResult, ApiSuccess};
-use crate::framework::{BooleanValue, OrderDirection, TlsVersion};
+use crate::framework::endpoint::{serialize_query, EndpointSpec, Paginated, RequestBody};
+use crate::framework::response::{ApiResult, ApiSuccess, ResultInfo};
+use crate::framework::{BooleanValue, OrderDirection, Secret, TlsVersion};
 use http::Method;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -24,8 +24,7 @@ impl EndpointSpec for CreateCustomHostname<'_> {
     }
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        let body = serde_json::to_string(&self.params).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(&self.params))
     }
 }
 
@@ -45,7 +44,7 @@ pub struct HostnameSslProperties {
     pub cloudflare_branding: Option<bool>,
     pub custom_cert_bundle: Option<Vec<CertificateBundle>>,
     pub custom_certificate: Option<String>,
-    pub custom_key: Option<String>,
+    pub custom_key: Option<Secret<String>>,
     pub method: Option<DomainValidationMethod>,
     pub settings: Option<SslSettings>,
     #[serde(rename = "type")]
@@ -84,7 +83,7 @@ pub enum DomainValidationMethod {
 #[derive(Debug, Serialize, Clone, Default)]
 pub struct CertificateBundle {
     pub custom_certificate: String,
-    pub custom_key: String,
+    pub custom_key: Secret<String>,
 }
 
 #[serde_with::skip_serializing_none]
@@ -280,8 +279,7 @@ impl EndpointSpec for EditCustomHostname<'_> {
 
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        let body = serde_json::to_string(&self.params).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(&self.params))
     }
 }
 
@@ -340,6 +338,13 @@ impl EndpointSpec for ListCustomHostname<'_> {
     }
 }
 
+impl Paginated for ListCustomHostname<'_> {
+    fn next_page(&mut self, result_info: &ResultInfo) -> bool {
+        self.params.page = result_info.next_page();
+        self.params.page.is_some()
+    }
+}
+
 #[serde_with::skip_serializing_none]
 #[derive(Serialize, Clone, Debug, Default)]
 pub struct ListCustomHostnameParams {
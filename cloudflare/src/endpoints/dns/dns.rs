@@ -1,5 +1,7 @@
-use crate::framework::endpoint::{serialize_query, EndpointSpec, Method, RequestBody};
-use crate::framework::response::{ApiResult, ApiSuccess};
+use crate::framework::endpoint::{
+    serialize_query, EndpointSpec, Method, Paginated, RequestBody, RequestError,
+};
+use crate::framework::response::{ApiResult, ApiSuccess, ResultInfo};
 /// <https://api.cloudflare.com/#dns-records-for-a-zone-properties>
 use crate::framework::{OrderDirection, SearchMatch};
 use chrono::offset::Utc;
@@ -30,6 +32,13 @@ impl EndpointSpec for ListDnsRecords<'_> {
     }
 }
 
+impl Paginated for ListDnsRecords<'_> {
+    fn next_page(&mut self, result_info: &ResultInfo) -> bool {
+        self.params.page = result_info.next_page();
+        self.params.page.is_some()
+    }
+}
+
 /// Create DNS Record
 /// <https://api.cloudflare.com/#dns-records-for-a-zone-create-dns-record>
 #[derive(Debug)]
@@ -48,10 +57,12 @@ impl EndpointSpec for CreateDnsRecord<'_> {
     fn path(&self) -> String {
         format!("zones/{}/dns_records", self.zone_identifier)
     }
+    fn validate(&self) -> Result<(), RequestError> {
+        self.params.validate()
+    }
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        let body = serde_json::to_string(&self.params).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(&self.params))
     }
 }
 
@@ -99,10 +110,12 @@ impl EndpointSpec for UpdateDnsRecord<'_> {
             self.zone_identifier, self.identifier
         )
     }
+    fn validate(&self) -> Result<(), RequestError> {
+        self.params.validate()
+    }
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        let body = serde_json::to_string(&self.params).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(&self.params))
     }
 }
 
@@ -201,10 +214,16 @@ impl EndpointSpec for BatchDnsRecords<'_> {
     fn path(&self) -> String {
         format!("zones/{}/dns_records/batch", self.zone_identifier)
     }
+    fn validate(&self) -> Result<(), RequestError> {
+        [&self.params.patches, &self.params.posts, &self.params.puts]
+            .into_iter()
+            .flatten()
+            .flatten()
+            .try_for_each(DnsRecordOperator::validate)
+    }
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        let body = serde_json::to_string(&self.params).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(&self.params))
     }
 }
 
@@ -235,6 +254,31 @@ pub struct DnsRecordOperator {
     pub content: DnsContent,
 }
 
+/// The TTL which lets Cloudflare pick the TTL of a record.
+pub const AUTOMATIC_TTL: u32 = 1;
+/// The minimum TTL of a record, in seconds. Only Enterprise zones may go below 60 seconds.
+pub const MIN_TTL: u32 = 30;
+/// The maximum TTL of a record, in seconds.
+pub const MAX_TTL: u32 = 86_400;
+
+impl DnsRecordOperator {
+    /// Checks that the TTL is automatic, or between `MIN_TTL` and `MAX_TTL`.
+    pub fn validate(&self) -> Result<(), RequestError> {
+        match self.ttl {
+            Some(ttl) if ttl != AUTOMATIC_TTL && !(MIN_TTL..=MAX_TTL).contains(&ttl) => {
+                Err(RequestError::invalid(
+                    "ttl",
+                    format!(
+                        "{ttl} must be {AUTOMATIC_TTL} (automatic), or between {MIN_TTL} and \
+                         {MAX_TTL} seconds"
+                    ),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 #[derive(Deserialize, Debug)]
 pub struct BatchDnsRecordsResponse {
     pub deletes: Option<Vec<DnsRecord>>,
@@ -318,9 +362,11 @@ impl EndpointSpec for OverwriteDnsRecord<'_> {
             self.zone_identifier, self.record_identifier
         )
     }
+    fn validate(&self) -> Result<(), RequestError> {
+        self.params.validate()
+    }
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        let body = serde_json::to_string(&self.params).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(&self.params))
     }
 }
@@ -1,5 +1,5 @@
-use crate::framework::endpoint::{serialize_query, EndpointSpec, RequestBody};
-use crate::framework::response::{ApiResult, ApiSuccess};
+use crate::framework::endpoint::{serialize_query, EndpointSpec, Paginated, RequestBody};
+use crate::framework::response::{ApiResult, ApiSuccess, ResultInfo};
 use crate::framework::OrderDirection;
 use http::Method;
 use serde::{Deserialize, Serialize};
@@ -40,8 +40,7 @@ impl EndpointSpec for CreateDestinationAddress<'_> {
 
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        let body = serde_json::to_string(&self.params).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(&self.params))
     }
 }
 
@@ -118,6 +117,13 @@ impl EndpointSpec for ListDestinationAddress<'_> {
     }
 }
 
+impl Paginated for ListDestinationAddress<'_> {
+    fn next_page(&mut self, result_info: &ResultInfo) -> bool {
+        self.params.page = result_info.next_page();
+        self.params.page.is_some()
+    }
+}
+
 #[serde_with::skip_serializing_none]
 #[derive(Serialize, Clone, Debug, Default)]
 pub struct ListDestinationAddressParams {
@@ -1,5 +1,5 @@
-use crate::framework::endpoint::{serialize_query, EndpointSpec, RequestBody};
-use crate::framework::response::{ApiResult, ApiSuccess};
+use crate::framework::endpoint::{serialize_query, EndpointSpec, Paginated, RequestBody};
+use crate::framework::response::{ApiResult, ApiSuccess, ResultInfo};
 use http::Method;
 use serde::{Deserialize, Serialize};
 
@@ -88,8 +88,7 @@ impl EndpointSpec for CreateRoutingRule<'_> {
 
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        let body = serde_json::to_string(&self.params).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(&self.params))
     }
 }
 
@@ -164,6 +163,13 @@ impl EndpointSpec for ListRoutingRule<'_> {
     }
 }
 
+impl Paginated for ListRoutingRule<'_> {
+    fn next_page(&mut self, result_info: &ResultInfo) -> bool {
+        self.params.page = result_info.next_page();
+        self.params.page.is_some()
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct UpdateRoutingRule<'a> {
     pub zone_identifier: &'a str,
@@ -197,8 +203,7 @@ impl EndpointSpec for UpdateRoutingRule<'_> {
 
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        let body = serde_json::to_string(&self.params).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(&self.params))
     }
 }
 
@@ -294,7 +299,6 @@ impl EndpointSpec for UpdateCatchAllRule<'_> {
 
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        let body = serde_json::to_string(&self.params).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(&self.params))
     }
 }
\ No newline at end of file
@@ -3,6 +3,7 @@ use super::WorkersSecret;
 use crate::framework::endpoint::{EndpointSpec, Method, RequestBody};
 
 use crate::framework::response::ApiSuccess;
+use crate::framework::Secret;
 use serde::Serialize;
 
 /// Create Secret
@@ -32,8 +33,7 @@ impl EndpointSpec for CreateSecret<'_> {
     }
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        let body = serde_json::to_string(&self.params).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(&self.params))
     }
 }
 
@@ -42,7 +42,7 @@ pub struct CreateSecretParams {
     /// the variable name of the secret that will be bound to the script
     pub name: String,
     /// the string value of the secret
-    pub text: String,
+    pub text: Secret<String>,
     // type of binding (e.g.secret_text)
     #[serde(rename = "type")]
     pub secret_type: String,
@@ -37,8 +37,7 @@ impl EndpointSpec for CreateTail<'_> {
     #[inline]
     fn body(&self) -> Option<RequestBody> {
         if self.params.url.is_some() {
-            let body = serde_json::to_string(&self.params).unwrap();
-            Some(RequestBody::Json(body))
+            Some(RequestBody::Serialize(&self.params))
         } else {
             None
         }
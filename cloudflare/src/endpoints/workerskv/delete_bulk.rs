@@ -1,5 +1,5 @@
 use crate::endpoints::workerskv::WorkersKvBulkResult;
-use crate::framework::endpoint::{EndpointSpec, Method, RequestBody};
+use crate::framework::endpoint::{EndpointSpec, Method, RequestBody, RequestError};
 use crate::framework::response::ApiSuccess;
 
 /// Remove multiple KV pairs from the namespace.
@@ -28,13 +28,15 @@ impl EndpointSpec for DeleteBulk<'_> {
             self.account_identifier, self.namespace_identifier
         )
     }
+    fn validate(&self) -> Result<(), RequestError> {
+        super::validate_bulk_len(self.bulk_keys.len())?;
+        self.bulk_keys
+            .iter()
+            .try_for_each(|key| super::validate_key(key))
+    }
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        if self.bulk_keys.len() > 10_000 {
-            panic!("Bulk delete request can only contain up to 10,000 keys.");
-        }
-        let body = serde_json::to_string(&self.bulk_keys).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(&self.bulk_keys))
     }
     // default content-type is already application/json
 }
@@ -1,4 +1,4 @@
-use crate::framework::endpoint::{EndpointSpec, Method};
+use crate::framework::endpoint::{EndpointSpec, Method, RequestError};
 use crate::framework::response::ApiSuccess;
 
 /// Remove a KV pair from the namespace.
@@ -28,4 +28,7 @@ impl EndpointSpec for DeleteKey<'_> {
             super::url_encode_key(self.key)
         )
     }
+    fn validate(&self) -> Result<(), RequestError> {
+        super::validate_key(self.key)
+    }
 }
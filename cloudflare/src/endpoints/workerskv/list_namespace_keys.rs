@@ -1,12 +1,36 @@
 use super::Key;
 
-use crate::framework::endpoint::{serialize_query, EndpointSpec, Method};
+use crate::framework::endpoint::{serialize_query, EndpointSpec, Method, Paginated};
 
-use crate::framework::response::ApiSuccess;
+use crate::framework::response::{ApiSuccess, ResultInfo};
 use serde::Serialize;
 
 /// Lists a namespace's keys.
 ///
+/// Keys are returned in batches of at most `limit` keys, along with a cursor pointing to the next
+/// batch. To iterate over every key without keeping the whole listing in memory, hand the endpoint
+/// to `Client::paginate`, which follows the cursors lazily:
+///
+/// ```no_run
+/// # async fn list(client: &cloudflare::framework::client::async_api::Client) {
+/// use cloudflare::endpoints::workerskv::list_namespace_keys::*;
+/// use futures_util::TryStreamExt;
+///
+/// let endpoint = ListNamespaceKeys {
+///     account_identifier: "account",
+///     namespace_identifier: "namespace",
+///     params: ListNamespaceKeysParams {
+///         prefix: Some("user:".to_string()),
+///         ..Default::default()
+///     },
+/// };
+/// let mut keys = std::pin::pin!(client.paginate(endpoint));
+/// while let Some(key) = keys.try_next().await.unwrap() {
+///     println!("{}", key.name);
+/// }
+/// # }
+/// ```
+///
 /// <https://developers.cloudflare.com/api/resources/kv/subresources/namespaces/subresources/keys/methods/list/>
 #[derive(Debug)]
 pub struct ListNamespaceKeys<'a> {
@@ -34,10 +58,56 @@ impl EndpointSpec for ListNamespaceKeys<'_> {
     }
 }
 
+impl Paginated for ListNamespaceKeys<'_> {
+    fn next_page(&mut self, result_info: &ResultInfo) -> bool {
+        self.params.cursor = result_info.next_cursor().map(str::to_owned);
+        self.params.cursor.is_some()
+    }
+}
+
 #[serde_with::skip_serializing_none]
 #[derive(Serialize, Clone, Debug, Default)]
 pub struct ListNamespaceKeysParams {
+    /// The number of keys to return per request, between 10 and 1000. Defaults to 1000.
     pub limit: Option<u16>,
+    /// Opaque token indicating the position from which to continue, as returned by a previous
+    /// request.
     pub cursor: Option<String>,
+    /// Only return keys that begin with this prefix.
     pub prefix: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_cursor() {
+        let mut endpoint = ListNamespaceKeys {
+            account_identifier: "account",
+            namespace_identifier: "namespace",
+            params: ListNamespaceKeysParams {
+                prefix: Some("user:".to_string()),
+                ..Default::default()
+            },
+        };
+
+        let result_info = ResultInfo {
+            count: Some(1000),
+            cursor: Some("6Ck1la0VxJ0djhidm1MdX2FyD".to_string()),
+            ..Default::default()
+        };
+        assert!(endpoint.next_page(&result_info));
+        assert_eq!(
+            endpoint.query().as_deref(),
+            Some("cursor=6Ck1la0VxJ0djhidm1MdX2FyD&prefix=user%3A")
+        );
+
+        let result_info = ResultInfo {
+            count: Some(12),
+            cursor: Some(String::new()),
+            ..Default::default()
+        };
+        assert!(!endpoint.next_page(&result_info));
+    }
+}
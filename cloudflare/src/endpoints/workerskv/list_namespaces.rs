@@ -1,8 +1,8 @@
 use super::WorkersKvNamespace;
 
-use crate::framework::endpoint::{serialize_query, EndpointSpec, Method};
+use crate::framework::endpoint::{serialize_query, EndpointSpec, Method, Paginated};
 
-use crate::framework::response::ApiSuccess;
+use crate::framework::response::{ApiSuccess, ResultInfo};
 use serde::Serialize;
 
 /// Returns the namespaces owned by an account.
@@ -30,6 +30,13 @@ impl EndpointSpec for ListNamespaces<'_> {
     }
 }
 
+impl Paginated for ListNamespaces<'_> {
+    fn next_page(&mut self, result_info: &ResultInfo) -> bool {
+        self.params.page = result_info.next_page();
+        self.params.page.is_some()
+    }
+}
+
 #[serde_with::skip_serializing_none]
 #[derive(Serialize, Clone, Debug, Default)]
 pub struct ListNamespacesParams {
@@ -1,3 +1,4 @@
+use crate::framework::endpoint::RequestError;
 use crate::framework::response::ApiResult;
 use chrono::DateTime;
 use chrono::{TimeZone, Utc};
@@ -77,6 +78,49 @@ fn url_encode_key(key: &str) -> String {
     urlencoding::encode(key).to_string()
 }
 
+/// The maximum length of a key's name, in bytes.
+pub const MAX_KEY_LEN: usize = 512;
+/// The minimum number of seconds for which a key can be visible before it expires.
+pub const MIN_EXPIRATION_TTL: i64 = 60;
+/// The maximum number of keys in a bulk request.
+pub const MAX_BULK_KEYS: usize = 10_000;
+
+fn validate_key(key: &str) -> Result<(), RequestError> {
+    if key.is_empty() {
+        return Err(RequestError::invalid("key", "the name must not be empty"));
+    }
+    if key.len() > MAX_KEY_LEN {
+        return Err(RequestError::invalid(
+            "key",
+            format!(
+                "the name is {} bytes long, but may be at most {MAX_KEY_LEN}",
+                key.len()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_expiration_ttl(expiration_ttl: Option<i64>) -> Result<(), RequestError> {
+    match expiration_ttl {
+        Some(ttl) if ttl < MIN_EXPIRATION_TTL => Err(RequestError::invalid(
+            "expiration_ttl",
+            format!("{ttl} seconds is less than the minimum of {MIN_EXPIRATION_TTL}"),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn validate_bulk_len(len: usize) -> Result<(), RequestError> {
+    if len > MAX_BULK_KEYS {
+        return Err(RequestError::invalid(
+            "bulk request",
+            format!("it contains {len} keys, but may contain at most {MAX_BULK_KEYS}"),
+        ));
+    }
+    Ok(())
+}
+
 #[serde_with::skip_serializing_none]
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct WorkersKvBulkResult {
@@ -1,5 +1,5 @@
 use crate::framework::endpoint::EndpointSpec;
-use crate::framework::endpoint::Method;
+use crate::framework::endpoint::{Method, RequestError};
 use crate::framework::response::ApiResult;
 
 /// Returns the value associated with the given key in the given namespace.
@@ -19,8 +19,6 @@ pub struct ReadKey<'a> {
 impl ApiResult for Vec<u8> {}
 
 impl EndpointSpec for ReadKey<'_> {
-    const IS_RAW_BODY: bool = true;
-
     type JsonResponse = ();
     type ResponseType = Vec<u8>;
 
@@ -35,4 +33,7 @@ impl EndpointSpec for ReadKey<'_> {
             super::url_encode_key(self.key)
         )
     }
+    fn validate(&self) -> Result<(), RequestError> {
+        super::validate_key(self.key)
+    }
 }
@@ -1,5 +1,5 @@
 use crate::framework::endpoint::EndpointSpec;
-use crate::framework::endpoint::Method;
+use crate::framework::endpoint::{Method, RequestError};
 use crate::framework::response::{ApiResult, ApiSuccess};
 
 /// Returns the metadata associated with the given key in the given namespace.
@@ -31,4 +31,7 @@ impl EndpointSpec for ReadKeyMetadata<'_> {
             super::url_encode_key(self.key)
         )
     }
+    fn validate(&self) -> Result<(), RequestError> {
+        super::validate_key(self.key)
+    }
 }
@@ -1,4 +1,4 @@
-use crate::framework::endpoint::{EndpointSpec, Method, RequestBody};
+use crate::framework::endpoint::{EndpointSpec, Method, RequestBody, RequestError};
 
 use crate::endpoints::workerskv::WorkersKvBulkResult;
 use crate::framework::response::ApiSuccess;
@@ -37,18 +37,27 @@ impl EndpointSpec for WriteBulk<'_> {
             self.account_identifier, self.namespace_identifier
         )
     }
+    fn validate(&self) -> Result<(), RequestError> {
+        super::validate_bulk_len(self.bulk_key_value_pairs.len())?;
+        for pair in &self.bulk_key_value_pairs {
+            super::validate_key(&pair.key)?;
+            super::validate_expiration_ttl(pair.expiration_ttl)?;
+        }
+        Ok(())
+    }
+    fn max_body_len(&self) -> Option<usize> {
+        Some(MAX_BULK_BODY_LEN)
+    }
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        if self.bulk_key_value_pairs.len() > 10_000 {
-            panic!("Bulk write request must have 10,000 key-value pairs or less.");
-        }
-        // TODO: The entire request size must be 100 megabytes or less.
-        let body = serde_json::to_string(&self.bulk_key_value_pairs).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(&self.bulk_key_value_pairs))
     }
     // default content-type is already application/json
 }
 
+/// The maximum length of the body of a bulk write, in bytes.
+pub const MAX_BULK_BODY_LEN: usize = 100 * 1000 * 1000;
+
 // TODO: Does not reflect the API documentation, but having everything Optional doesn't make sense either
 #[serde_with::skip_serializing_none]
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -59,3 +68,69 @@ pub struct KeyValuePair {
     pub expiration_ttl: Option<i64>,
     pub base64: Option<bool>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framework::auth::Credentials;
+    use crate::framework::client::codec::build_request;
+    use crate::framework::response::ApiFailure;
+    use crate::framework::Environment;
+
+    fn pair(key: &str, expiration_ttl: Option<i64>) -> KeyValuePair {
+        KeyValuePair {
+            key: key.to_owned(),
+            value: "value".to_owned(),
+            expiration: None,
+            expiration_ttl,
+            base64: None,
+        }
+    }
+
+    fn write_bulk(bulk_key_value_pairs: Vec<KeyValuePair>) -> WriteBulk<'static> {
+        WriteBulk {
+            account_identifier: "account",
+            namespace_identifier: "namespace",
+            bulk_key_value_pairs,
+        }
+    }
+
+    #[test]
+    fn validates_limits() {
+        assert!(write_bulk(vec![pair("key", Some(60)); 10_000])
+            .validate()
+            .is_ok());
+
+        let invalid = [
+            (write_bulk(vec![pair("key", None); 10_001]), "bulk request"),
+            (write_bulk(vec![pair(&"k".repeat(513), None)]), "key"),
+            (write_bulk(vec![pair("key", Some(59))]), "expiration_ttl"),
+        ];
+        for (endpoint, expected) in invalid {
+            match endpoint.validate() {
+                Err(RequestError::Invalid { field, .. }) => assert_eq!(field, expected),
+                result => panic!("Expected an invalid {expected}, got {result:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn limits_body_len() {
+        let mut large = pair("key", None);
+        large.value = "v".repeat(MAX_BULK_BODY_LEN);
+        let credentials = Credentials::UserAuthToken {
+            token: "token".into(),
+        };
+        let result = build_request(
+            &write_bulk(vec![large]),
+            &Environment::Production,
+            &credentials,
+        );
+        match result.map(|_| ()) {
+            Err(ApiFailure::InvalidEndpoint(RequestError::Invalid { field, .. })) => {
+                assert_eq!(field, "request body")
+            }
+            result => panic!("Expected an invalid request body, got {result:?}"),
+        }
+    }
+}
@@ -1,8 +1,7 @@
 use crate::framework::endpoint::{serialize_query, EndpointSpec, MultipartBody, MultipartPart};
-use crate::framework::endpoint::{Method, RequestBody};
+use crate::framework::endpoint::{Method, RequestBody, RequestError, StreamBody};
 use crate::framework::response::ApiSuccess;
 use serde::Serialize;
-use std::borrow::Cow;
 
 /// Write a value identified by a key.
 ///
@@ -52,17 +51,16 @@ impl EndpointSpec for WriteKey<'_> {
     fn query(&self) -> Option<String> {
         serialize_query(&self.params)
     }
+    fn validate(&self) -> Result<(), RequestError> {
+        super::validate_key(self.key)?;
+        super::validate_expiration_ttl(self.params.expiration_ttl)
+    }
     #[inline]
     fn body(&self) -> Option<RequestBody> {
         match &self.body {
             WriteKeyBody::Value(value) => Some(RequestBody::Raw(value.clone())),
             WriteKeyBody::Metadata(metadata) => Some(RequestBody::MultiPart(metadata)),
-        }
-    }
-    fn content_type(&self) -> Option<Cow<'static, str>> {
-        match &self.body {
-            WriteKeyBody::Value(_) => Some(Cow::Borrowed("application/octet-stream")),
-            WriteKeyBody::Metadata(_) => Some(Cow::Borrowed("multipart/form-data")),
+            WriteKeyBody::Stream(stream) => Some(RequestBody::Stream(stream)),
         }
     }
 }
@@ -89,9 +87,7 @@ impl MultipartBody for WriteKeyBodyMetadata {
         vec![
             (
                 "metadata".to_string(),
-                MultipartPart::Text(
-                    serde_json::to_string(&self.metadata).expect("Failed to serialize metadata"),
-                ),
+                MultipartPart::Text(self.metadata.to_string()),
             ),
             (
                 "value".to_string(),
@@ -101,10 +97,17 @@ impl MultipartBody for WriteKeyBodyMetadata {
     }
 }
 
-#[derive(Serialize, Clone, Debug)]
+/// The body of `WriteKey`.
+///
+/// `Value` and `Metadata` are copied into the request. Large values should be streamed instead.
+#[derive(Serialize, Debug)]
 pub enum WriteKeyBody {
     /// The value to store.
     Value(Vec<u8>),
     /// The value to store with metadata.
     Metadata(WriteKeyBodyMetadata),
+    /// The value to store, read as the request is sent, e.g. from a file. The request is not
+    /// retried, as the value can only be read once.
+    #[serde(skip)]
+    Stream(StreamBody),
 }
@@ -41,8 +41,7 @@ impl EndpointSpec for EditZoneSetting<'_> {
 
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        let body = serde_json::to_string(&self).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(self))
     }
 }
 
@@ -72,8 +71,7 @@ impl EndpointSpec for GetZoneSetting<'_> {
 
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        let body = serde_json::to_string(&self).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(self))
     }
 }
 
@@ -1,8 +1,8 @@
 use crate::endpoints::account::AccountDetails;
 use crate::endpoints::zones::plan::Plan;
 use crate::framework::endpoint::{serialize_query, RequestBody};
-use crate::framework::endpoint::{EndpointSpec, Method};
-use crate::framework::response::{ApiResult, ApiSuccess};
+use crate::framework::endpoint::{EndpointSpec, Method, Paginated};
+use crate::framework::response::{ApiResult, ApiSuccess, ResultInfo};
 use crate::framework::{OrderDirection, SearchMatch};
 use chrono::offset::Utc;
 use chrono::DateTime;
@@ -32,6 +32,13 @@ impl EndpointSpec for ListZones {
     }
 }
 
+impl Paginated for ListZones {
+    fn next_page(&mut self, result_info: &ResultInfo) -> bool {
+        self.params.page = result_info.next_page();
+        self.params.page.is_some()
+    }
+}
+
 /// Zone Details
 /// <https://api.cloudflare.com/#zone-zone-details>
 #[derive(Debug)]
@@ -87,8 +94,7 @@ impl EndpointSpec for CreateZone<'_> {
 
     #[inline]
     fn body(&self) -> Option<RequestBody> {
-        let body = serde_json::to_string(&self.params).unwrap();
-        Some(RequestBody::Json(body))
+        Some(RequestBody::Serialize(&self.params))
     }
 }
 
@@ -0,0 +1,40 @@
+mod provider;
+mod refresh;
+
+pub use provider::{
+    ChainProvider, CredentialsError, CredentialsProvider, EnvProvider, ProfileProvider,
+};
+pub use refresh::{CachedProvider, TokenFileProvider};
+
+use crate::framework::Secret;
+
+/// The keys are wrapped in [`Secret`], so printing credentials never reveals them.
+#[derive(Clone, Debug)]
+pub enum Credentials {
+    UserAuthKey { email: String, key: Secret<String> },
+    UserAuthToken { token: Secret<String> },
+    Service { key: Secret<String> },
+}
+
+impl Credentials {
+    /// The headers which authenticate a request. Their values are zeroed when they are dropped.
+    pub fn headers(&self) -> Vec<(&'static str, Secret<String>)> {
+        match self {
+            Self::UserAuthKey { email, key } => {
+                vec![
+                    ("X-Auth-Email", email.as_str().into()),
+                    ("X-Auth-Key", key.clone()),
+                ]
+            }
+            Self::UserAuthToken { token } => {
+                let value = format!("Bearer {}", token.expose_secret());
+                vec![("Authorization", value.into())]
+            }
+            Self::Service { key } => vec![("X-Auth-User-Service-Key", key.clone())],
+        }
+    }
+}
+
+pub trait AuthClient {
+    fn auth(self, credentials: &Credentials) -> Self;
+}
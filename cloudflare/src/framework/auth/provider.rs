@@ -0,0 +1,410 @@
+//! Sources of the credentials which authenticate API requests, see [`CredentialsProvider`].
+use crate::framework::auth::Credentials;
+use crate::framework::Secret;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A source of the credentials which authenticate API requests.
+///
+/// The clients ask their provider for credentials before each request, so a provider may return
+/// different credentials over time. `Credentials` are a provider which always returns themselves.
+///
+/// Providers are called for every request, so those which read files or call other services
+/// should be wrapped in a `CachedProvider`.
+pub trait CredentialsProvider: Send + Sync {
+    fn credentials(&self) -> Result<Credentials, CredentialsError>;
+
+    /// Called when the API rejected the credentials (with a 401 or 403 status), before the client
+    /// asks for credentials again. If they changed, the request is retried once with them.
+    ///
+    /// Providers which cache credentials should forget them. Defaults to doing nothing.
+    fn refresh(&self) {}
+}
+
+impl CredentialsProvider for Credentials {
+    fn credentials(&self) -> Result<Credentials, CredentialsError> {
+        Ok(self.clone())
+    }
+}
+
+impl<P: CredentialsProvider + ?Sized> CredentialsProvider for Arc<P> {
+    fn credentials(&self) -> Result<Credentials, CredentialsError> {
+        (**self).credentials()
+    }
+
+    fn refresh(&self) {
+        (**self).refresh()
+    }
+}
+
+impl<P: CredentialsProvider + ?Sized> CredentialsProvider for Box<P> {
+    fn credentials(&self) -> Result<Credentials, CredentialsError> {
+        (**self).credentials()
+    }
+
+    fn refresh(&self) {
+        (**self).refresh()
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum CredentialsError {
+    /// The provider has no credentials, e.g. because its environment variables are not set.
+    /// `ChainProvider` moves on to its next provider.
+    #[error("no credentials found: {0}")]
+    NotFound(String),
+    /// The provider has incomplete credentials, e.g. an API key without an email.
+    #[error("invalid credentials: {0}")]
+    Invalid(String),
+    #[error("could not read {}: {source}", .path.display())]
+    Io { path: PathBuf, source: io::Error },
+    /// The message gives the position of the error, but doesn't quote the file, which may hold
+    /// keys.
+    #[error("could not parse {}: {message}", .path.display())]
+    Parse { path: PathBuf, message: String },
+}
+
+/// The credentials found in the environment or in a profile, before they are checked.
+#[derive(Debug, Default, Deserialize)]
+struct RawCredentials {
+    api_token: Option<Secret<String>>,
+    api_key: Option<Secret<String>>,
+    email: Option<String>,
+    service_key: Option<Secret<String>>,
+}
+
+impl RawCredentials {
+    /// `source` describes where the credentials come from, in errors.
+    fn into_credentials(self, source: &str) -> Result<Credentials, CredentialsError> {
+        match self {
+            RawCredentials {
+                api_token: Some(token),
+                ..
+            } => Ok(Credentials::UserAuthToken { token }),
+            RawCredentials {
+                api_key: Some(key),
+                email: Some(email),
+                ..
+            } => Ok(Credentials::UserAuthKey { email, key }),
+            RawCredentials {
+                api_key: Some(_), ..
+            } => Err(CredentialsError::Invalid(format!(
+                "{source} has an API key, but no email"
+            ))),
+            RawCredentials { email: Some(_), .. } => Err(CredentialsError::Invalid(format!(
+                "{source} has an email, but no API key"
+            ))),
+            RawCredentials {
+                service_key: Some(key),
+                ..
+            } => Ok(Credentials::Service { key }),
+            _ => Err(CredentialsError::NotFound(format!(
+                "{source} has no credentials"
+            ))),
+        }
+    }
+}
+
+/// Reads credentials from environment variables:
+///
+/// - `CLOUDFLARE_API_TOKEN`, for an API token.
+/// - `CLOUDFLARE_API_KEY` and `CLOUDFLARE_EMAIL`, for a global API key and the email of its user.
+/// - `CLOUDFLARE_API_USER_SERVICE_KEY`, for an Origin CA service key.
+///
+/// If several are set, they are preferred in this order. Empty variables are ignored. The variables
+/// are read each time credentials are requested.
+#[derive(Clone, Debug, Default)]
+pub struct EnvProvider;
+
+impl EnvProvider {
+    pub const API_TOKEN: &'static str = "CLOUDFLARE_API_TOKEN";
+    pub const API_KEY: &'static str = "CLOUDFLARE_API_KEY";
+    pub const EMAIL: &'static str = "CLOUDFLARE_EMAIL";
+    pub const SERVICE_KEY: &'static str = "CLOUDFLARE_API_USER_SERVICE_KEY";
+
+    fn credentials_from(
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Credentials, CredentialsError> {
+        let var = |name| var(name).filter(|value| !value.is_empty());
+        RawCredentials {
+            api_token: var(Self::API_TOKEN).map(Secret::new),
+            api_key: var(Self::API_KEY).map(Secret::new),
+            email: var(Self::EMAIL),
+            service_key: var(Self::SERVICE_KEY).map(Secret::new),
+        }
+        .into_credentials("the environment")
+    }
+}
+
+impl CredentialsProvider for EnvProvider {
+    fn credentials(&self) -> Result<Credentials, CredentialsError> {
+        EnvProvider::credentials_from(|name| std::env::var(name).ok())
+    }
+}
+
+/// Reads credentials from a named profile of a TOML file, in which each table is a profile:
+///
+/// ```toml
+/// [default]
+/// api_token = "..."
+///
+/// [legacy]
+/// api_key = "..."
+/// email = "user@example.com"
+/// ```
+///
+/// A profile holds an `api_token`, an `api_key` and its `email`, or a `service_key`, which are
+/// preferred in this order. Other keys, such as an `account_id`, are ignored. The file is read each
+/// time credentials are requested.
+#[derive(Clone, Debug)]
+pub struct ProfileProvider {
+    path: PathBuf,
+    profile: String,
+}
+
+impl ProfileProvider {
+    /// The profile used when `CLOUDFLARE_PROFILE` is not set.
+    pub const DEFAULT_PROFILE: &'static str = "default";
+    /// The variable which names the file of `from_env`.
+    pub const CONFIG_FILE: &'static str = "CLOUDFLARE_CONFIG_FILE";
+    /// The variable which names the profile of `from_env`.
+    pub const PROFILE: &'static str = "CLOUDFLARE_PROFILE";
+
+    pub fn new(path: impl Into<PathBuf>, profile: impl Into<String>) -> ProfileProvider {
+        ProfileProvider {
+            path: path.into(),
+            profile: profile.into(),
+        }
+    }
+
+    /// Reads the profile named by `CLOUDFLARE_PROFILE`, or the default profile, from the file
+    /// named by `CLOUDFLARE_CONFIG_FILE`, or `~/.cloudflare/credentials.toml`.
+    ///
+    /// Returns `None` if no file is named and the home directory is unknown.
+    pub fn from_env() -> Option<ProfileProvider> {
+        let var = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+        let path = match var(Self::CONFIG_FILE) {
+            Some(path) => PathBuf::from(path),
+            None => {
+                let home = var("HOME").or_else(|| var("USERPROFILE"))?;
+                Path::new(&home)
+                    .join(".cloudflare")
+                    .join("credentials.toml")
+            }
+        };
+        let profile = std::env::var(Self::PROFILE)
+            .ok()
+            .filter(|profile| !profile.is_empty())
+            .unwrap_or_else(|| Self::DEFAULT_PROFILE.to_owned());
+        Some(ProfileProvider::new(path, profile))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn profile(&self) -> &str {
+        &self.profile
+    }
+}
+
+impl CredentialsProvider for ProfileProvider {
+    fn credentials(&self) -> Result<Credentials, CredentialsError> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => Secret::new(content),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return Err(CredentialsError::NotFound(format!(
+                    "{} does not exist",
+                    self.path.display()
+                )));
+            }
+            Err(source) => {
+                return Err(CredentialsError::Io {
+                    path: self.path.clone(),
+                    source,
+                })
+            }
+        };
+        let mut profiles: HashMap<String, RawCredentials> = toml::from_str(content.expose_secret())
+            .map_err(|error| {
+                let content = content.expose_secret();
+                let line = error
+                    .span()
+                    .map(|span| content[..span.start].matches('\n').count() + 1);
+                CredentialsError::Parse {
+                    path: self.path.clone(),
+                    message: match line {
+                        Some(line) => format!("line {line}: {}", error.message()),
+                        None => error.message().to_owned(),
+                    },
+                }
+            })?;
+        let source = format!("profile `{}` of {}", self.profile, self.path.display());
+        match profiles.remove(&self.profile) {
+            Some(profile) => profile.into_credentials(&source),
+            None => Err(CredentialsError::NotFound(format!(
+                "{source} does not exist"
+            ))),
+        }
+    }
+}
+
+/// Tries providers in order, and returns the credentials of the first one which has some.
+///
+/// Providers without credentials are skipped, but other errors, such as an unreadable file, are
+/// returned without trying the next providers.
+#[derive(Clone, Default)]
+pub struct ChainProvider {
+    providers: Vec<Arc<dyn CredentialsProvider>>,
+}
+
+impl ChainProvider {
+    pub fn new() -> ChainProvider {
+        ChainProvider::default()
+    }
+
+    /// Tries the environment variables (see `EnvProvider`), then the profile named by the
+    /// environment (see `ProfileProvider::from_env`).
+    pub fn from_env() -> ChainProvider {
+        let chain = ChainProvider::new().with(EnvProvider);
+        match ProfileProvider::from_env() {
+            Some(profile) => chain.with(profile),
+            None => chain,
+        }
+    }
+
+    /// Adds a provider, which is tried after the previous ones.
+    pub fn with(mut self, provider: impl CredentialsProvider + 'static) -> ChainProvider {
+        self.providers.push(Arc::new(provider));
+        self
+    }
+}
+
+impl CredentialsProvider for ChainProvider {
+    fn credentials(&self) -> Result<Credentials, CredentialsError> {
+        let mut not_found = Vec::new();
+        for provider in &self.providers {
+            match provider.credentials() {
+                Err(CredentialsError::NotFound(reason)) => not_found.push(reason),
+                result => return result,
+            }
+        }
+        if not_found.is_empty() {
+            not_found.push("the chain has no providers".to_owned());
+        }
+        Err(CredentialsError::NotFound(not_found.join("; ")))
+    }
+
+    fn refresh(&self) {
+        for provider in &self.providers {
+            provider.refresh();
+        }
+    }
+}
+
+impl fmt::Debug for ChainProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChainProvider")
+            .field("providers", &self.providers.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn reads_environment() {
+        let credentials = EnvProvider::credentials_from(vars(&[
+            ("CLOUDFLARE_API_TOKEN", "token"),
+            ("CLOUDFLARE_API_KEY", "key"),
+        ]));
+        assert!(matches!(
+            credentials,
+            Ok(Credentials::UserAuthToken { token }) if token.expose_secret() == "token"
+        ));
+
+        let credentials = EnvProvider::credentials_from(vars(&[
+            ("CLOUDFLARE_API_TOKEN", ""),
+            ("CLOUDFLARE_API_KEY", "key"),
+            ("CLOUDFLARE_EMAIL", "user@example.com"),
+        ]));
+        assert!(matches!(
+            credentials,
+            Ok(Credentials::UserAuthKey { email, key })
+                if email == "user@example.com" && key.expose_secret() == "key"
+        ));
+
+        let credentials = EnvProvider::credentials_from(vars(&[("CLOUDFLARE_API_KEY", "key")]));
+        assert!(matches!(credentials, Err(CredentialsError::Invalid(_))));
+
+        let credentials = EnvProvider::credentials_from(vars(&[]));
+        assert!(matches!(credentials, Err(CredentialsError::NotFound(_))));
+    }
+
+    #[test]
+    fn reads_profiles_and_chains() {
+        let path = std::env::temp_dir().join(format!("cloudflare-{}.toml", fastrand::u64(..)));
+        std::fs::write(
+            &path,
+            "[default]\napi_token = \"token\"\n\n[service]\nservice_key = \"key\"\n",
+        )
+        .unwrap();
+
+        let credentials = ProfileProvider::new(&path, "service").credentials();
+        assert!(
+            matches!(credentials, Ok(Credentials::Service { key }) if key.expose_secret() == "key")
+        );
+
+        let chain = ChainProvider::new()
+            .with(ProfileProvider::new(&path, "missing"))
+            .with(ProfileProvider::new(
+                path.with_extension("missing"),
+                "default",
+            ))
+            .with(ProfileProvider::new(&path, "default"));
+        let credentials = chain.credentials();
+        assert!(
+            matches!(credentials, Ok(Credentials::UserAuthToken { token }) if token.expose_secret() == "token")
+        );
+
+        std::fs::write(
+            &path,
+            "[default]\naccount_id = \"abc\"\napi_token = \"token\"\n",
+        )
+        .unwrap();
+        let credentials = chain.credentials();
+        assert!(
+            matches!(credentials, Ok(Credentials::UserAuthToken { token }) if token.expose_secret() == "token")
+        );
+
+        std::fs::write(&path, "[default]\napi_tokens = \"token\"\n").unwrap();
+        let credentials = chain.credentials();
+        assert!(matches!(credentials, Err(CredentialsError::NotFound(_))));
+
+        std::fs::write(&path, "[default]\napi_token = \"hunter2\n").unwrap();
+        let error = chain.credentials().unwrap_err().to_string();
+        assert!(
+            error.contains("line 2") && !error.contains("hunter2"),
+            "{error}"
+        );
+
+        std::fs::remove_file(&path).unwrap();
+        let credentials = chain.credentials();
+        assert!(matches!(credentials, Err(CredentialsError::NotFound(_))));
+    }
+}
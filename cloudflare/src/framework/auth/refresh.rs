@@ -0,0 +1,188 @@
+//! Providers which refresh credentials over time, so that they can be rotated without rebuilding
+//! the client.
+use crate::framework::auth::{Credentials, CredentialsError, CredentialsProvider};
+use crate::framework::Secret;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Caches the credentials of another provider for a time to live, after which they are requested
+/// again.
+///
+/// The cache is also emptied when the API rejects the credentials, see
+/// `CredentialsProvider::refresh`.
+#[derive(Debug)]
+pub struct CachedProvider<P> {
+    inner: P,
+    ttl: Duration,
+    cached: Mutex<Option<(Credentials, Instant)>>,
+}
+
+impl<P: CredentialsProvider> CachedProvider<P> {
+    pub fn new(inner: P, ttl: Duration) -> CachedProvider<P> {
+        CachedProvider {
+            inner,
+            ttl,
+            cached: Mutex::new(None),
+        }
+    }
+
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+
+    fn cached(&self) -> MutexGuard<'_, Option<(Credentials, Instant)>> {
+        self.cached
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl<P: CredentialsProvider> CredentialsProvider for CachedProvider<P> {
+    fn credentials(&self) -> Result<Credentials, CredentialsError> {
+        let mut cached = self.cached();
+        match &*cached {
+            Some((credentials, fetched)) if fetched.elapsed() < self.ttl => Ok(credentials.clone()),
+            _ => {
+                let credentials = self.inner.credentials()?;
+                *cached = Some((credentials.clone(), Instant::now()));
+                Ok(credentials)
+            }
+        }
+    }
+
+    fn refresh(&self) {
+        *self.cached() = None;
+        self.inner.refresh();
+    }
+}
+
+/// Reads an API token from a file, which is read again whenever it is modified. Surrounding
+/// whitespace is ignored.
+///
+/// This suits tokens which are rotated by rewriting the file, e.g. by a secrets manager. The
+/// modification time of the file is checked before each request.
+#[derive(Debug)]
+pub struct TokenFileProvider {
+    path: PathBuf,
+    cached: Mutex<Option<(Secret<String>, SystemTime)>>,
+}
+
+impl TokenFileProvider {
+    pub fn new(path: impl Into<PathBuf>) -> TokenFileProvider {
+        TokenFileProvider {
+            path: path.into(),
+            cached: Mutex::new(None),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn cached(&self) -> MutexGuard<'_, Option<(Secret<String>, SystemTime)>> {
+        self.cached
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn io_error(&self, source: io::Error) -> CredentialsError {
+        if source.kind() == io::ErrorKind::NotFound {
+            return CredentialsError::NotFound(format!("{} does not exist", self.path.display()));
+        }
+        CredentialsError::Io {
+            path: self.path.clone(),
+            source,
+        }
+    }
+}
+
+impl CredentialsProvider for TokenFileProvider {
+    fn credentials(&self) -> Result<Credentials, CredentialsError> {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|error| self.io_error(error))?;
+        let mut cached = self.cached();
+        let token = match &*cached {
+            Some((token, cached_modified)) if *cached_modified == modified => token.clone(),
+            _ => {
+                let content = Secret::new(
+                    std::fs::read_to_string(&self.path).map_err(|error| self.io_error(error))?,
+                );
+                let token = Secret::from(content.expose_secret().trim());
+                if token.expose_secret().is_empty() {
+                    return Err(CredentialsError::NotFound(format!(
+                        "{} is empty",
+                        self.path.display()
+                    )));
+                }
+                *cached = Some((token.clone(), modified));
+                token
+            }
+        };
+        Ok(Credentials::UserAuthToken { token })
+    }
+
+    fn refresh(&self) {
+        // The file may have been rewritten within the precision of its modification time.
+        *self.cached() = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    struct CountingProvider(AtomicU32);
+
+    impl CredentialsProvider for CountingProvider {
+        fn credentials(&self) -> Result<Credentials, CredentialsError> {
+            let count = self.0.fetch_add(1, Ordering::Relaxed) + 1;
+            Ok(Credentials::UserAuthToken {
+                token: format!("token-{count}").into(),
+            })
+        }
+    }
+
+    fn token(credentials: Result<Credentials, CredentialsError>) -> String {
+        match credentials {
+            Ok(Credentials::UserAuthToken { token }) => token.expose_secret().clone(),
+            other => panic!("Expected a token, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn caches_until_expired_or_refreshed() {
+        let provider = CachedProvider::new(CountingProvider(AtomicU32::new(0)), Duration::MAX);
+        assert_eq!(token(provider.credentials()), "token-1");
+        assert_eq!(token(provider.credentials()), "token-1");
+        provider.refresh();
+        assert_eq!(token(provider.credentials()), "token-2");
+
+        let provider = CachedProvider::new(CountingProvider(AtomicU32::new(0)), Duration::ZERO);
+        assert_eq!(token(provider.credentials()), "token-1");
+        assert_eq!(token(provider.credentials()), "token-2");
+    }
+
+    #[test]
+    fn reads_rotated_token_files() {
+        let path = std::env::temp_dir().join(format!("cloudflare-{}.token", fastrand::u64(..)));
+        let provider = TokenFileProvider::new(&path);
+        assert!(matches!(
+            provider.credentials(),
+            Err(CredentialsError::NotFound(_))
+        ));
+
+        std::fs::write(&path, "first\n").unwrap();
+        assert_eq!(token(provider.credentials()), "first");
+
+        std::fs::write(&path, "second\n").unwrap();
+        // The modification time may not have changed yet, but a refresh reads the file again.
+        provider.refresh();
+        assert_eq!(token(provider.credentials()), "second");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
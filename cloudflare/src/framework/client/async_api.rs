@@ -1,35 +1,66 @@
-use crate::framework::client::ClientConfig;
-use crate::framework::endpoint::{EndpointSpec, MultipartPart, RequestBody};
-use crate::framework::response::ResponseConverter;
+#[cfg(feature = "reqwest")]
+use crate::framework::auth::{AuthClient, Credentials};
+use crate::framework::client::batch::{BatchItem, BatchOptions, BatchOrder};
+use crate::framework::client::codec;
+use crate::framework::client::middleware::{Middleware, ReceivedBody};
+use crate::framework::client::options::RequestOptions;
+use crate::framework::client::rate_limit::RateLimiter;
+use crate::framework::client::retry::{Attempts, RetryPolicy, Step};
+use crate::framework::client::trace::RequestSpan;
+#[cfg(feature = "reqwest")]
+use crate::framework::client::transport::ReqwestTransport;
+use crate::framework::client::transport::{BodyStream, Transport, TransportError};
+use crate::framework::client::{ApiClient, ClientConfig};
+use crate::framework::endpoint::{EndpointSpec, Paginated};
 use crate::framework::{
-    auth::{AuthClient, Credentials},
+    auth::CredentialsProvider,
     response::ApiResponse,
-    response::{ApiErrors, ApiFailure, ApiSuccess},
+    response::{ApiFailure, ApiSuccess, Response},
     Environment,
 };
-use std::borrow::Cow;
+use bytes::Bytes;
+use futures_util::stream::{self, Stream, StreamExt, TryStreamExt};
+use std::future::Future;
+#[cfg(feature = "reqwest")]
 use std::net::SocketAddr;
+use std::sync::Arc;
 
 /// A Cloudflare API client that makes requests asynchronously.
+///
+/// The client is given `Credentials`, or any `CredentialsProvider`, which it asks for credentials
+/// before each request. Requests whose credentials are rejected are retried once with refreshed
+/// credentials, see `CredentialsProvider::refresh`.
+///
+/// Clones share the same transport (and thus connection pool) and rate limiter.
 // TODO: Rename to AsyncClient?
+#[derive(Clone)]
 pub struct Client {
     environment: Environment,
-    credentials: Credentials,
-    http_client: reqwest::Client,
+    credentials: Arc<dyn CredentialsProvider>,
+    transport: Arc<dyn Transport>,
+    retry_policy: Option<RetryPolicy>,
+    rate_limiter: Option<RateLimiter>,
+    middleware: Vec<Arc<dyn Middleware>>,
 }
 
+#[cfg(feature = "reqwest")]
 impl AuthClient for reqwest::RequestBuilder {
     fn auth(mut self, credentials: &Credentials) -> Self {
-        for (k, v) in credentials.headers() {
-            self = self.header(k, v);
+        for (name, value) in credentials.headers() {
+            self = match codec::sensitive_header(&value) {
+                Ok(value) => self.header(name, value),
+                // The builder reports the invalid value when the request is built.
+                Err(_) => self.header(name, value.expose_secret().as_str()),
+            };
         }
         self
     }
 }
 
 impl Client {
+    #[cfg(feature = "reqwest")]
     pub fn new(
-        credentials: Credentials,
+        credentials: impl CredentialsProvider + 'static,
         config: ClientConfig,
         environment: Environment,
     ) -> Result<Client, crate::framework::Error> {
@@ -39,7 +70,7 @@ impl Client {
         {
             // There is no resolve method in wasm.
             if let Some(address) = config.resolve_ip {
-                let url = url::Url::from(&environment);
+                let url = url::Url::try_from(&environment)?;
                 builder = builder.resolve(
                     url.host_str()
                         .expect("Environment url should have a hostname"),
@@ -55,23 +86,53 @@ impl Client {
 
         Ok(Client {
             environment,
-            credentials,
-            http_client,
+            credentials: Arc::new(credentials),
+            transport: Arc::new(ReqwestTransport::new(http_client)),
+            retry_policy: config.retry_policy,
+            rate_limiter: config.rate_limit.as_ref().map(RateLimiter::new),
+            middleware: config.middleware,
         })
     }
+
+    /// Creates a client which sends its requests with the given `reqwest` client.
+    ///
+    /// Only the retry policy, rate limit and middleware of the config are used. The other settings
+    /// (timeout, default headers, ...) are those of `client`.
+    #[cfg(feature = "reqwest")]
     pub fn new_with_client(
         client: reqwest::Client,
-        credentials: Credentials,
+        credentials: impl CredentialsProvider + 'static,
+        config: ClientConfig,
         environment: Environment,
     ) -> Result<Client, crate::framework::Error> {
-        Ok(Client {
-            environment,
+        Ok(Client::new_with_transport(
+            ReqwestTransport::new(client),
             credentials,
-            http_client: client,
-        })
+            config,
+            environment,
+        ))
+    }
+
+    /// Creates a client which sends its requests through the given transport.
+    ///
+    /// Only the retry policy, rate limit and middleware of the config are used. The other settings
+    /// (timeout, default headers, ...) are up to the transport.
+    pub fn new_with_transport(
+        transport: impl Transport + 'static,
+        credentials: impl CredentialsProvider + 'static,
+        config: ClientConfig,
+        environment: Environment,
+    ) -> Client {
+        Client {
+            environment,
+            credentials: Arc::new(credentials),
+            transport: Arc::new(transport),
+            retry_policy: config.retry_policy,
+            rate_limiter: config.rate_limit.as_ref().map(RateLimiter::new),
+            middleware: config.middleware,
+        }
     }
 
-    //noinspection RsConstantConditionIf
     /// Issue an API request of the given type.
     pub async fn request<Endpoint>(
         &self,
@@ -79,303 +140,300 @@ impl Client {
     ) -> ApiResponse<Endpoint::ResponseType>
     where
         Endpoint: EndpointSpec + Send + Sync,
-        Endpoint::ResponseType: ResponseConverter<Endpoint::JsonResponse>,
     {
-        // Build the request
-        let mut request = self
-            .http_client
-            .request(endpoint.method(), endpoint.url(&self.environment));
-
-        if let Some(body) = endpoint.body() {
-            match body {
-                RequestBody::Json(json) => {
-                    request = request.body(json);
-                }
-                RequestBody::Raw(bytes) => {
-                    request = request.body(bytes);
-                }
-                RequestBody::MultiPart(multipart) => {
-                    let mut form = reqwest::multipart::Form::new();
-                    for (name, part) in multipart.parts() {
-                        match part {
-                            MultipartPart::Text(text) => {
-                                form = form.text(name, text);
-                            }
-                            MultipartPart::Bytes(bytes) => {
-                                form = form.part(name, reqwest::multipart::Part::bytes(bytes));
-                            }
-                        }
-                    }
-                    request = request.multipart(form);
-                }
-            }
-            // Reqwest::RequestBuilder::multipart sets the content type for us.
-            match endpoint.content_type() {
-                None | Some(Cow::Borrowed("multipart/form-data")) => {}
-                Some(content_type) => {
-                    request = request.header(reqwest::header::CONTENT_TYPE, content_type.as_ref());
-                }
-            }
-        }
-
-        request = request.auth(&self.credentials);
-        let response = request.send().await?;
-
-        // The condition is necessary, even if a warning is present.
-        // The constant is overridden in some cases.
-        if Endpoint::IS_RAW_BODY {
-            map_api_response_raw::<Endpoint>(response).await
-        } else {
-            map_api_response_json::<Endpoint>(response).await
-        }
+        self.request_with_meta(endpoint)
+            .await
+            .map(Response::into_result)
     }
-}
 
-// If the response is 2XX and parses, return Success.
-// If the response is 2XX and doesn't parse, return Invalid.
-// If the response isn't 2XX, return Failure, with API errors if they were included.
-async fn map_api_response_raw<Endpoint>(
-    resp: reqwest::Response,
-) -> Result<Endpoint::ResponseType, ApiFailure>
-where
-    Endpoint: EndpointSpec,
-    Endpoint::ResponseType: ResponseConverter<Endpoint::JsonResponse>,
-{
-    let status = resp.status();
-    if status.is_success() {
-        let bytes = resp.bytes().await.map_err(ApiFailure::Invalid)?.to_vec();
-        Ok(Endpoint::ResponseType::from_raw(bytes))
-    } else {
-        let parsed: Result<ApiErrors, reqwest::Error> = resp.json().await;
-        let errors = parsed.unwrap_or_default();
-        Err(ApiFailure::Error(status, errors))
-    }
-}
-
-async fn map_api_response_json<Endpoint>(
-    resp: reqwest::Response,
-) -> Result<Endpoint::ResponseType, ApiFailure>
-where
-    Endpoint: EndpointSpec,
-    Endpoint::ResponseType: ResponseConverter<Endpoint::JsonResponse>,
-{
-    let status = resp.status();
-    if status.is_success() {
-        // let text = resp.text().await.map_err(ApiFailure::Invalid)?;
-        // println!("text: {}", text);
-        // let parsed: Result<ApiSuccess<Endpoint::JsonResponse>, serde_json::Error> = serde_json::from_str(&text);
-        // match parsed {
-        //     Ok(success) => Ok(Endpoint::ResponseType::from_json(success)),
-        //     Err(e) => {panic!("{}", e)}
-        // }
-
-        let parsed: Result<ApiSuccess<Endpoint::JsonResponse>, reqwest::Error> = resp.json().await;
-        match parsed {
-            Ok(success) => Ok(Endpoint::ResponseType::from_json(success)),
-            Err(e) => Err(ApiFailure::Invalid(e)),
-        }
-    } else {
-        let parsed: Result<ApiErrors, reqwest::Error> = resp.json().await;
-        let errors = parsed.unwrap_or_default();
-        Err(ApiFailure::Error(status, errors))
+    /// Issue an API request of the given type, with options which only apply to this request,
+    /// e.g. a longer timeout or a cancellation token.
+    pub async fn request_with<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+        options: &RequestOptions,
+    ) -> ApiResponse<Endpoint::ResponseType>
+    where
+        Endpoint: EndpointSpec + Send + Sync,
+    {
+        self.request_with_options(endpoint, options)
+            .await
+            .map(Response::into_result)
     }
-}
-
-// TODO: Refactor this to test the blocking_api as well
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::framework::auth::Credentials;
-    use crate::framework::client::ClientConfig;
-    use crate::framework::endpoint::RequestBody;
-    use crate::framework::endpoint::{serialize_query, EndpointSpec};
-    use crate::framework::response::{ApiFailure, ApiResult, ApiSuccess};
-    use crate::framework::Environment;
-    use mockito::{Matcher, Server};
-    use regex;
-    use regex::Regex;
-    use serde::{Deserialize, Serialize};
-    use serde_json::json;
-    use tokio;
-
-    //region Endpoint that returns JSON (ApiSuccess).
-    #[derive(Debug)]
-    struct DummyJsonEndpoint;
 
-    #[derive(Debug, Deserialize)]
-    struct DummyJsonResponse {
-        message: String,
+    /// Issue an API request of the given type, and return its result along with the status and
+    /// headers of the response.
+    pub async fn request_with_meta<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+    ) -> ApiResponse<Response<Endpoint::ResponseType>>
+    where
+        Endpoint: EndpointSpec + Send + Sync,
+    {
+        self.request_with_options(endpoint, &RequestOptions::default())
+            .await
     }
 
-    impl ApiResult for DummyJsonResponse {}
-
-    impl EndpointSpec for DummyJsonEndpoint {
-        type JsonResponse = DummyJsonResponse;
-        type ResponseType = ApiSuccess<Self::JsonResponse>;
-
-        fn method(&self) -> reqwest::Method {
-            reqwest::Method::GET
-        }
-
-        fn path(&self) -> String {
-            "/dummy/json".into()
+    async fn request_with_options<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+        options: &RequestOptions,
+    ) -> ApiResponse<Response<Endpoint::ResponseType>>
+    where
+        Endpoint: EndpointSpec + Send + Sync,
+    {
+        let span = RequestSpan::new(endpoint);
+        let request = span.instrument(async {
+            let (mut request, upload) =
+                codec::build_request(endpoint, &self.environment, &*self.credentials)?;
+            codec::apply_options(&mut request, options);
+            let response = match upload {
+                None => {
+                    self.send(self.attempts(request), |request| {
+                        self.transport.send(request)
+                    })
+                    .await?
+                }
+                // A streamed body can only be read once, so the request is not retried.
+                Some(body) => {
+                    let mut body = Some(body);
+                    self.send(self.attempts(request).once(), |request| {
+                        let body = body.take().expect("the request is only sent once");
+                        self.transport.send_upload(request, body)
+                    })
+                    .await?
+                }
+            };
+            span.record_response(&response);
+            let (status, headers) = (response.status(), response.headers().clone());
+            let result = codec::parse_response(endpoint, &self.environment, response)?;
+            Ok(Response {
+                status,
+                headers,
+                result,
+            })
+        });
+        match &options.cancellation {
+            Some(token) => token
+                .run_until_cancelled(request)
+                .await
+                .unwrap_or(Err(ApiFailure::Cancelled)),
+            None => request.await,
         }
     }
-    //endregion
-
-    //region Endpoint that returns raw bytes.
-    #[derive(Debug)]
-    struct DummyRawEndpoint;
-
-    impl EndpointSpec for DummyRawEndpoint {
-        const IS_RAW_BODY: bool = true;
-        type JsonResponse = ();
-        type ResponseType = Vec<u8>;
-
-        fn method(&self) -> reqwest::Method {
-            reqwest::Method::GET
-        }
 
-        fn path(&self) -> String {
-            "/dummy/raw".into()
-        }
+    /// Fetches every page of a list endpoint, one after the other, and streams the listed items.
+    ///
+    /// Pages are only requested once the items of the previous page have been consumed.
+    /// The stream ends after the first error.
+    pub fn paginate<'a, Endpoint>(
+        &'a self,
+        endpoint: Endpoint,
+    ) -> impl Stream<Item = ApiResponse<<Endpoint::JsonResponse as IntoIterator>::Item>> + 'a
+    where
+        Endpoint: Paginated
+            + EndpointSpec<ResponseType = ApiSuccess<<Endpoint as EndpointSpec>::JsonResponse>>
+            + Send
+            + Sync
+            + 'a,
+        Endpoint::JsonResponse: IntoIterator,
+    {
+        stream::try_unfold(Some(endpoint), move |endpoint| async move {
+            let Some(mut endpoint) = endpoint else {
+                return Ok::<_, ApiFailure>(None);
+            };
+            let page = self.request(&endpoint).await?;
+            let has_next = page
+                .result_info
+                .as_ref()
+                .is_some_and(|result_info| endpoint.next_page(result_info));
+            let items = stream::iter(page.result.into_iter().map(Ok));
+            Ok(Some((items, has_next.then_some(endpoint))))
+        })
+        .try_flatten()
     }
-    //endregion
-
-    //region Endpoint that returns nothing.
-    #[derive(Debug)]
-    struct DummyNothingEndpoint;
 
-    impl EndpointSpec for DummyNothingEndpoint {
-        type JsonResponse = ();
-        type ResponseType = ApiSuccess<Self::JsonResponse>;
-
-        fn method(&self) -> reqwest::Method {
-            reqwest::Method::GET
-        }
-
-        fn path(&self) -> String {
-            "/dummy/nothing".into()
+    /// Sends a request for each endpoint, with at most `options.concurrency` requests in flight,
+    /// and streams their results, e.g. to get a setting of every zone.
+    ///
+    /// The requests go through the rate limiter and retry policy of the client, like any other.
+    /// Endpoints are only taken from `endpoints` once their request can be sent. Results can be
+    /// counted by error code with a `BatchSummary`.
+    pub fn batch<'a, Endpoint, I>(
+        &'a self,
+        endpoints: I,
+        options: BatchOptions,
+    ) -> impl Stream<Item = BatchItem<Endpoint, Endpoint::ResponseType>> + 'a
+    where
+        Endpoint: EndpointSpec + Send + Sync + 'a,
+        I: IntoIterator<Item = Endpoint>,
+        I::IntoIter: 'a,
+    {
+        let BatchOptions {
+            concurrency,
+            order,
+            request,
+        } = options;
+        let request = Arc::new(request);
+        let requests =
+            stream::iter(endpoints.into_iter().enumerate()).map(move |(index, endpoint)| {
+                let request = Arc::clone(&request);
+                async move {
+                    let result = self.request_with(&endpoint, &request).await;
+                    BatchItem {
+                        index,
+                        endpoint,
+                        result,
+                    }
+                }
+            });
+        let concurrency = concurrency.max(1);
+        match order {
+            BatchOrder::Input => requests.buffered(concurrency).left_stream(),
+            BatchOrder::Completion => requests.buffer_unordered(concurrency).right_stream(),
         }
     }
-    //endregion
-
-    //region Endpoint that sends a JSON request.
-    #[derive(Debug)]
-    struct DummyJsonRequestEndpoint;
-
-    impl EndpointSpec for DummyJsonRequestEndpoint {
-        type JsonResponse = ();
-        type ResponseType = ApiSuccess<Self::JsonResponse>;
 
-        fn method(&self) -> reqwest::Method {
-            reqwest::Method::POST
-        }
-
-        fn path(&self) -> String {
-            "/dummy/json".into()
-        }
-
-        fn body(&self) -> Option<RequestBody> {
-            Some(RequestBody::Json(json!({"key": "value"}).to_string()))
-        }
+    /// Issue a request to an endpoint which returns raw bytes, and stream its body as it is
+    /// received, instead of reading it into memory.
+    ///
+    /// The request is retried according to the client's retry policy until its response headers
+    /// are received, but errors which happen while reading the body are returned by the stream.
+    /// A streamed request body is read into memory first.
+    pub async fn request_stream<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+    ) -> ApiResponse<Response<BodyStream>>
+    where
+        Endpoint: EndpointSpec<ResponseType = Vec<u8>> + Send + Sync,
+    {
+        let span = RequestSpan::new(endpoint);
+        span.instrument(async {
+            let (mut request, upload) =
+                codec::build_request(endpoint, &self.environment, &*self.credentials)?;
+            if let Some(body) = upload {
+                *request.body_mut() = body.into_bytes().await?;
+            }
+            let response = self
+                .send(self.attempts(request), |request| {
+                    self.transport.send_streaming(request)
+                })
+                .await?;
+            span.record_head(&response);
+            let (parts, body) = response.into_parts();
+            if !parts.status.is_success() {
+                let body = body
+                    .try_fold(Vec::new(), |mut body, chunk| async move {
+                        body.extend_from_slice(&chunk);
+                        Ok(body)
+                    })
+                    .await?;
+                return Err(codec::parse_failure(
+                    endpoint,
+                    &self.environment,
+                    parts,
+                    &body,
+                ));
+            }
+            codec::check_content_type::<Vec<u8>>(&parts.headers)?;
+            Ok(Response {
+                status: parts.status,
+                headers: parts.headers,
+                result: body,
+            })
+        })
+        .await
     }
-    //endregion
-
-    //region Endpoint that sends raw bytes.
-    #[derive(Debug)]
-    struct DummyRawRequestEndpoint;
 
-    impl EndpointSpec for DummyRawRequestEndpoint {
-        const IS_RAW_BODY: bool = true;
-        type JsonResponse = ();
-        type ResponseType = Vec<u8>;
-
-        fn method(&self) -> reqwest::Method {
-            reqwest::Method::POST
-        }
-
-        fn path(&self) -> String {
-            "/dummy/raw".into()
-        }
-
-        fn body(&self) -> Option<RequestBody> {
-            Some(RequestBody::Raw(b"raw content".to_vec()))
-        }
+    /// The attempts at sending `request` with the retry policy, rate limiter, middleware and
+    /// credentials of the client.
+    fn attempts(&self, request: http::Request<Bytes>) -> Attempts<'_> {
+        Attempts::new(
+            request,
+            self.retry_policy.as_ref(),
+            self.rate_limiter.as_ref(),
+            &self.middleware,
+            &*self.credentials,
+        )
     }
-    //endregion
-
-    //region Endpoint that sends a multipart request.
-    #[derive(Debug)]
-    struct DummyMultipartEndpoint;
-
-    impl EndpointSpec for DummyMultipartEndpoint {
-        type JsonResponse = ();
-        type ResponseType = ApiSuccess<Self::JsonResponse>;
-
-        fn method(&self) -> reqwest::Method {
-            reqwest::Method::POST
-        }
 
-        fn path(&self) -> String {
-            "/dummy/multipart".into()
-        }
-
-        fn body(&self) -> Option<RequestBody> {
-            Some(RequestBody::MultiPart(&DummyMultipart))
+    /// Sends the request of `attempts` through `transport`, waiting and retrying as they decide.
+    async fn send<B, F>(
+        &self,
+        mut attempts: Attempts<'_>,
+        mut transport: impl FnMut(http::Request<Bytes>) -> F,
+    ) -> ApiResponse<http::Response<B>>
+    where
+        B: ReceivedBody,
+        F: Future<Output = Result<http::Response<B>, TransportError>>,
+    {
+        let mut step = attempts.start();
+        loop {
+            step = match step {
+                Step::Wait(delay) => {
+                    futures_timer::Delay::new(delay).await;
+                    attempts.resume()
+                }
+                Step::Send(request) => attempts.received(transport(request).await),
+                Step::Done(result) => return result,
+            };
         }
     }
+}
 
-    struct DummyMultipart;
-
-    impl crate::framework::endpoint::MultipartBody for DummyMultipart {
-        fn parts(&self) -> Vec<(String, MultipartPart)> {
-            vec![("key".into(), MultipartPart::Text("value".into()))]
-        }
+impl ApiClient for Client {
+    fn request<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+    ) -> impl Future<Output = ApiResponse<Endpoint::ResponseType>>
+    where
+        Endpoint: EndpointSpec + Send + Sync,
+    {
+        Client::request(self, endpoint)
     }
-    //endregion
 
-    //region Endpoint that sends a request with query parameters.
-    #[derive(Debug)]
-    struct DummyJsonRequestWithQueryEndpoint;
-
-    #[derive(Debug, Serialize)]
-    struct DummyJsonRequestWithQueryParams {
-        key: String,
+    fn request_with<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+        options: &RequestOptions,
+    ) -> impl Future<Output = ApiResponse<Endpoint::ResponseType>>
+    where
+        Endpoint: EndpointSpec + Send + Sync,
+    {
+        Client::request_with(self, endpoint, options)
     }
 
-    impl EndpointSpec for DummyJsonRequestWithQueryEndpoint {
-        type JsonResponse = ();
-        type ResponseType = ApiSuccess<Self::JsonResponse>;
-
-        fn method(&self) -> reqwest::Method {
-            reqwest::Method::POST
-        }
-
-        fn path(&self) -> String {
-            "/dummy/json".into()
-        }
-
-        fn query(&self) -> Option<String> {
-            serialize_query(&DummyJsonRequestWithQueryParams {
-                key: "value".into(),
-            })
-        }
+    fn request_with_meta<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+    ) -> impl Future<Output = ApiResponse<Response<Endpoint::ResponseType>>>
+    where
+        Endpoint: EndpointSpec + Send + Sync,
+    {
+        Client::request_with_meta(self, endpoint)
     }
-    //endregion
+}
 
-    fn create_test_client(url: String) -> Client {
-        let environment = Environment::Custom(url);
-        let credentials = Credentials::UserAuthToken {
-            token: "dummy".into(),
-        };
-        let config = ClientConfig::default();
-        Client::new(credentials, config, environment).unwrap()
-    }
+// The tests of the clients send their requests to a mock server with `reqwest`.
+#[cfg(all(test, feature = "reqwest"))]
+mod tests {
+    use super::*;
+    use crate::endpoints::workerskv::write_key::{WriteKey, WriteKeyBody, WriteKeyParams};
+    use crate::framework::auth::{Credentials, CredentialsError, CredentialsProvider};
+    use crate::framework::client::test_support::*;
+    use crate::framework::client::ClientConfig;
+    use crate::framework::endpoint::RequestError;
+    use crate::framework::endpoint::StreamBody;
+    use crate::framework::response::{ApiFailure, ErrorCode};
+    use mockito::{Matcher, Server};
+    use regex;
+    use regex::Regex;
+    use serde_json::json;
+    use std::sync::Mutex;
 
-    /// Test that the client can successfully request a JSON endpoint.
-    #[tokio::test]
-    async fn test_json_endpoint_success() {
+    /// Test that both clients can successfully request a JSON endpoint.
+    #[test]
+    fn test_json_endpoint_success() {
         let body = json!({
             "result": {"message": "Hello, World!"},
             "result_info": null,
@@ -384,7 +442,7 @@ mod tests {
             "success": true
         });
 
-        let mut server = Server::new_async().await;
+        let mut server = Server::new();
         let mock = server
             .mock("GET", "/dummy/json")
             .with_status(200)
@@ -393,25 +451,28 @@ mod tests {
             .match_header("content-type", Matcher::Missing)
             .match_query(Matcher::Missing)
             .match_body(Matcher::Missing)
+            .expect(CLIENTS)
             .create();
 
-        let client = create_test_client(server.url());
-        let response = client.request(&DummyJsonEndpoint).await;
+        let responses = TestClients::new(server.url()).request(&DummyJsonEndpoint);
 
         mock.assert();
-        let response = response.unwrap();
-        assert_eq!(response.result.message, "Hello, World!");
-        assert_eq!(response.result_info, None);
-        assert!(response.messages.is_empty());
-        assert!(response.errors.is_empty());
+        assert_eq!(responses.len(), CLIENTS);
+        for response in responses {
+            let response = response.unwrap();
+            assert_eq!(response.result.message, "Hello, World!");
+            assert_eq!(response.result_info, None);
+            assert!(response.messages.is_empty());
+            assert!(response.errors.is_empty());
+        }
     }
 
-    /// Test that the client can successfully request a raw endpoint.
-    #[tokio::test]
-    async fn test_raw_endpoint_success() {
+    /// Test that both clients can successfully request a raw endpoint.
+    #[test]
+    fn test_raw_endpoint_success() {
         let raw_body = b"raw content".to_vec();
 
-        let mut server = Server::new_async().await;
+        let mut server = Server::new();
         let mock = server
             .mock("GET", "/dummy/raw")
             .with_status(200)
@@ -420,24 +481,27 @@ mod tests {
             .match_header("content-type", Matcher::Missing)
             .match_query(Matcher::Missing)
             .match_body(Matcher::Missing)
+            .expect(CLIENTS)
             .create();
 
-        let client = create_test_client(server.url());
-        let response = client.request(&DummyRawEndpoint).await.unwrap();
+        let responses = TestClients::new(server.url()).request(&DummyRawEndpoint);
 
         mock.assert();
-        assert_eq!(response, raw_body);
+        assert_eq!(responses.len(), CLIENTS);
+        for response in responses {
+            assert_eq!(response.unwrap(), raw_body);
+        }
     }
 
-    /// Test that the client can handle an endpoint that returns an error.
-    #[tokio::test]
-    async fn test_endpoint_failure() {
+    /// Test that both clients can handle an endpoint that returns an error.
+    #[test]
+    fn test_endpoint_failure() {
         let body = json!({
             "errors": [{"code": 123, "message": "Something went wrong", "other": {}}],
             "other": {}
         });
 
-        let mut server = Server::new_async().await;
+        let mut server = Server::new();
         let mock = server
             .mock("GET", "/dummy/json")
             .with_status(400)
@@ -446,25 +510,27 @@ mod tests {
             .match_header("content-type", Matcher::Missing)
             .match_query(Matcher::Missing)
             .match_body(Matcher::Missing)
+            .expect(CLIENTS)
             .create();
 
-        let client = create_test_client(server.url());
-        let result = client.request(&DummyJsonEndpoint).await;
+        let responses = TestClients::new(server.url()).request(&DummyJsonEndpoint);
 
         mock.assert();
-        assert!(result.is_err());
-        if let Err(ApiFailure::Error(status, errors)) = result {
-            assert_eq!(status.as_u16(), 400);
-            assert!(!errors.errors.is_empty());
-            assert_eq!(errors.errors[0].code, 123);
-        } else {
-            panic!("Expected error result");
+        assert_eq!(responses.len(), CLIENTS);
+        for result in responses {
+            if let Err(ApiFailure::Error(status, errors, _)) = result {
+                assert_eq!(status.as_u16(), 400);
+                assert!(!errors.errors.is_empty());
+                assert_eq!(errors.errors[0].code, 123);
+            } else {
+                panic!("Expected error result");
+            }
         }
     }
 
-    /// Test that the client can handle an endpoint that returns nothing.
-    #[tokio::test]
-    async fn test_nothing_endpoint_success() {
+    /// Test that both clients can handle an endpoint that returns nothing.
+    #[test]
+    fn test_nothing_endpoint_success() {
         let body = json!({
             "result": null,
             "result_info": null,
@@ -473,7 +539,7 @@ mod tests {
             "success": true
         });
 
-        let mut server = Server::new_async().await;
+        let mut server = Server::new();
         let mock = server
             .mock("GET", "/dummy/nothing")
             .with_status(200)
@@ -482,22 +548,25 @@ mod tests {
             .match_header("content-type", Matcher::Missing)
             .match_query(Matcher::Missing)
             .match_body(Matcher::Missing)
+            .expect(CLIENTS)
             .create();
 
-        let client = create_test_client(server.url());
-        let response = client.request(&DummyNothingEndpoint).await;
+        let responses = TestClients::new(server.url()).request(&DummyNothingEndpoint);
 
         mock.assert();
-        let response = response.unwrap();
-        assert!(matches!(response.result, ()));
-        assert_eq!(response.result_info, None);
-        assert!(response.messages.is_empty());
-        assert!(response.errors.is_empty());
+        assert_eq!(responses.len(), CLIENTS);
+        for response in responses {
+            let response = response.unwrap();
+            assert!(matches!(response.result, ()));
+            assert_eq!(response.result_info, None);
+            assert!(response.messages.is_empty());
+            assert!(response.errors.is_empty());
+        }
     }
 
-    /// Test that the client can successfully send a JSON request.
-    #[tokio::test]
-    async fn test_json_body_success() {
+    /// Test that both clients can successfully send a JSON request.
+    #[test]
+    fn test_json_body_success() {
         let body = json!({
             "result": null,
             "result_info": null,
@@ -506,7 +575,7 @@ mod tests {
             "success": true
         });
 
-        let mut server = Server::new_async().await;
+        let mut server = Server::new();
         let mock = server
             .mock("POST", "/dummy/json")
             .with_status(200)
@@ -515,20 +584,21 @@ mod tests {
             .match_header("content-type", "application/json")
             .match_query(Matcher::Missing)
             .match_body(Matcher::Json(json!({"key": "value"})))
+            .expect(CLIENTS)
             .create();
 
-        let client = create_test_client(server.url());
-        let _ = client.request(&DummyJsonRequestEndpoint).await;
+        let responses = TestClients::new(server.url()).request(&DummyJsonRequestEndpoint);
 
         mock.assert();
+        assert!(responses.iter().all(Result::is_ok));
     }
 
-    /// Test that the client can successfully send a raw request.
-    #[tokio::test]
-    async fn test_raw_body_success() {
+    /// Test that both clients can successfully send a raw request.
+    #[test]
+    fn test_raw_body_success() {
         let raw_body = b"raw content".to_vec();
 
-        let mut server = Server::new_async().await;
+        let mut server = Server::new();
         let mock = server
             .mock("POST", "/dummy/raw")
             .with_status(200)
@@ -537,17 +607,18 @@ mod tests {
             .match_header("content-type", "application/octet-stream")
             .match_query(Matcher::Missing)
             .match_body(raw_body)
+            .expect(CLIENTS)
             .create();
 
-        let client = create_test_client(server.url());
-        let _ = client.request(&DummyRawRequestEndpoint).await;
+        let responses = TestClients::new(server.url()).request(&DummyRawRequestEndpoint);
 
         mock.assert();
+        assert!(responses.iter().all(Result::is_ok));
     }
 
-    /// Test that the client can successfully send a multipart request.
-    #[tokio::test]
-    async fn test_multipart_body_success() {
+    /// Test that both clients can successfully send a multipart request.
+    #[test]
+    fn test_multipart_body_success() {
         let body = json!({
             "result": null,
             "result_info": null,
@@ -556,7 +627,7 @@ mod tests {
             "success": true
         });
 
-        let mut server = Server::new_async().await;
+        let mut server = Server::new();
 
         let mock = server
             .mock("POST", "/dummy/multipart")
@@ -578,17 +649,18 @@ mod tests {
                 .unwrap();
                 re.is_match(&body)
             })
+            .expect(CLIENTS)
             .create();
 
-        let client = create_test_client(server.url());
-        let _ = client.request(&DummyMultipartEndpoint).await;
+        let responses = TestClients::new(server.url()).request(&DummyMultipartEndpoint);
 
         mock.assert();
+        assert!(responses.iter().all(Result::is_ok));
     }
 
-    /// Test that the client can successfully send a request with query parameters.
-    #[tokio::test]
-    async fn test_query_parameters_success() {
+    /// Test that both clients can successfully send a request with query parameters.
+    #[test]
+    fn test_query_parameters_success() {
         let body = json!({
             "result": null,
             "result_info": null,
@@ -597,7 +669,7 @@ mod tests {
             "success": true
         });
 
-        let mut server = Server::new_async().await;
+        let mut server = Server::new();
         let mock = server
             .mock("POST", "/dummy/json")
             .with_status(200)
@@ -606,11 +678,475 @@ mod tests {
             .match_header("content-type", Matcher::Missing)
             .match_query(Matcher::UrlEncoded("key".into(), "value".into()))
             .match_body(Matcher::Missing)
+            .expect(CLIENTS)
+            .create();
+
+        let responses = TestClients::new(server.url()).request(&DummyJsonRequestWithQueryEndpoint);
+
+        mock.assert();
+        assert!(responses.iter().all(Result::is_ok));
+    }
+
+    /// Test that both clients fetch every page of a paginated endpoint.
+    #[test]
+    fn test_paginate() {
+        let page = |page: u32, items: &[&str]| {
+            let items: Vec<_> = items.iter().map(|m| json!({"message": m})).collect();
+            json!({
+                "result": items,
+                "result_info": {"page": page, "per_page": 2, "count": items.len(), "total_pages": 2},
+                "success": true
+            })
+            .to_string()
+        };
+
+        let mut server = Server::new();
+        let first = server
+            .mock("GET", "/dummy/list")
+            .match_query(Matcher::Missing)
+            .with_status(200)
+            .with_body(page(1, &["a", "b"]))
+            .expect(CLIENTS)
+            .create();
+        let second = server
+            .mock("GET", "/dummy/list")
+            .match_query(Matcher::UrlEncoded("page".into(), "2".into()))
+            .with_status(200)
+            .with_body(page(2, &["c"]))
+            .expect(CLIENTS)
+            .create();
+
+        let clients = TestClients::new(server.url());
+        let items = clients
+            .client
+            .paginate(DummyListEndpoint { page: None })
+            .try_collect::<Vec<DummyJsonResponse>>();
+        #[allow(unused_mut)]
+        let mut listings = vec![clients.runtime.block_on(items).unwrap()];
+        #[cfg(feature = "blocking")]
+        listings.push(
+            clients
+                .blocking
+                .paginate(DummyListEndpoint { page: None })
+                .collect::<Result<_, _>>()
+                .unwrap(),
+        );
+
+        first.assert();
+        second.assert();
+        for items in listings {
+            let messages: Vec<_> = items.into_iter().map(|item| item.message).collect();
+            assert_eq!(messages, ["a", "b", "c"]);
+        }
+    }
+
+    /// Test that both clients send the same multipart request and parse the same response.
+    #[test]
+    fn test_clients_share_request_building() {
+        let body = json!({
+            "result": null,
+            "messages": [],
+            "errors": [],
+            "success": true
+        });
+
+        let mut server = Server::new();
+        let mock = server
+            .mock("POST", "/dummy/multipart")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(body.to_string())
+            .match_header("authorization", "Bearer dummy")
+            .match_header(
+                "content-type",
+                Matcher::Regex("multipart/form-data; boundary=.*".into()),
+            )
+            .match_body(Matcher::Regex(r#"name="key"\s+value"#.into()))
+            .expect(CLIENTS)
+            .create();
+
+        let responses = TestClients::new(server.url()).request(&DummyMultipartEndpoint);
+
+        mock.assert();
+        assert_eq!(responses.len(), CLIENTS);
+        assert!(responses.iter().all(Result::is_ok));
+    }
+
+    /// Test that both clients send streamed bodies, once.
+    #[test]
+    fn test_clients_send_streamed_bodies() {
+        let stream_body = || {
+            let chunks = ["hello", " ", "world"].map(|chunk| Ok(Bytes::from(chunk)));
+            StreamBody::new(stream::iter(chunks), Some(11))
+        };
+
+        let mut server = Server::new();
+        let mock = server
+            .mock("PUT", "/dummy/stream")
+            .with_status(503)
+            .match_header("content-type", "application/octet-stream")
+            .match_header("content-length", "11")
+            .match_body("hello world")
+            .expect(CLIENTS)
+            .create();
+
+        let clients = TestClients::with_config(server.url(), fast_retry_config);
+        let endpoint = DummyStreamEndpoint {
+            body: stream_body(),
+        };
+        let failure = clients
+            .runtime
+            .block_on(clients.client.request(&endpoint))
+            .unwrap_err();
+        assert_eq!(
+            failure.status(),
+            Some(http::StatusCode::SERVICE_UNAVAILABLE)
+        );
+        // The body was consumed by the first request.
+        let failure = clients
+            .runtime
+            .block_on(clients.client.request(&endpoint))
+            .unwrap_err();
+        assert!(matches!(failure, ApiFailure::Transport(_)));
+
+        #[cfg(feature = "blocking")]
+        {
+            let endpoint = DummyStreamEndpoint {
+                body: stream_body(),
+            };
+            let failure = clients.blocking.request(&endpoint).unwrap_err();
+            assert_eq!(
+                failure.status(),
+                Some(http::StatusCode::SERVICE_UNAVAILABLE)
+            );
+        }
+
+        mock.assert();
+    }
+
+    /// Test that both clients write KV values read from a reader.
+    #[test]
+    fn test_clients_write_kv_values_from_readers() {
+        let mut server = Server::new();
+        let mock = server
+            .mock(
+                "PUT",
+                "/accounts/account/storage/kv/namespaces/namespace/values/key",
+            )
+            .match_query(Matcher::Any)
+            .match_header("content-type", "application/octet-stream")
+            .match_body("value")
+            .with_status(200)
+            .with_body(r#"{"result": null, "success": true}"#)
+            .expect(CLIENTS)
+            .create();
+
+        let write_key = || WriteKey {
+            account_identifier: "account",
+            namespace_identifier: "namespace",
+            key: "key",
+            params: WriteKeyParams::default(),
+            body: WriteKeyBody::Stream(StreamBody::from_reader(
+                std::io::Cursor::new(b"value"),
+                Some(5),
+            )),
+        };
+        let clients = TestClients::new(server.url());
+        let response = clients
+            .runtime
+            .block_on(clients.client.request(&write_key()));
+        assert!(response.is_ok());
+        #[cfg(feature = "blocking")]
+        assert!(clients.blocking.request(&write_key()).is_ok());
+
+        mock.assert();
+    }
+
+    /// Test that both clients send files in multipart requests.
+    #[test]
+    fn test_clients_send_multipart_files() {
+        let path = std::env::temp_dir().join(format!("worker-{}.js", fastrand::u64(..)));
+        std::fs::write(&path, "export default {}").unwrap();
+
+        let mut server = Server::new();
+        let mock = server
+            .mock("PUT", "/dummy/file")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"result": null, "success": true}"#)
+            .match_body(Matcher::Regex(
+                concat!(
+                    r#"name="worker.js"; filename="worker-\d+.js"\s+"#,
+                    r#"Content-Type: application/javascript\+module\s+"#,
+                    r#"export default \{\}\s+--"#,
+                )
+                .into(),
+            ))
+            .expect(CLIENTS)
+            .create();
+
+        let endpoint = DummyFileEndpoint { path: path.clone() };
+        let responses = TestClients::new(server.url()).request(&endpoint);
+        std::fs::remove_file(path).unwrap();
+
+        mock.assert();
+        assert!(responses.iter().all(Result::is_ok));
+    }
+
+    /// Test that both clients report unparseable responses instead of panicking.
+    #[test]
+    fn test_clients_share_response_parsing() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("GET", "/dummy/json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("not json")
+            .expect(CLIENTS)
+            .create();
+
+        let responses = TestClients::new(server.url()).request(&DummyJsonEndpoint);
+
+        mock.assert();
+        assert_eq!(responses.len(), CLIENTS);
+        assert!(responses
+            .iter()
+            .all(|response| matches!(response, Err(ApiFailure::Deserialize(_)))));
+    }
+
+    /// Test that API errors carry the request, the response headers and the unparsed body.
+    #[test]
+    fn test_clients_add_context_to_errors() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("GET", "/dummy/json")
+            .with_status(502)
+            .with_header("content-type", "text/html")
+            .with_header("cf-ray", "8c1c7bbd7d3f3b1a-CDG")
+            .with_body("<html>Bad gateway</html>")
+            .expect(CLIENTS)
+            .create();
+
+        let responses = TestClients::new(server.url()).request(&DummyJsonEndpoint);
+
+        mock.assert();
+        assert_eq!(responses.len(), CLIENTS);
+        for response in responses {
+            let failure = response.unwrap_err();
+            assert_eq!(failure.status(), Some(http::StatusCode::BAD_GATEWAY));
+            assert_eq!(failure.cf_ray(), Some("8c1c7bbd7d3f3b1a-CDG"));
+            let context = failure.context().unwrap();
+            assert_eq!(context.method, http::Method::GET);
+            assert_eq!(context.url, format!("{}/dummy/json", server.url()));
+            assert_eq!(
+                context.raw_body.as_deref(),
+                Some("<html>Bad gateway</html>")
+            );
+        }
+    }
+
+    /// Test that endpoints which cannot be built, or which break a limit of the API, are not sent.
+    #[test]
+    fn test_clients_do_not_send_invalid_endpoints() {
+        let mut server = Server::new();
+        let mock = server.mock("PUT", Matcher::Any).expect(0).create();
+
+        let key = "k".repeat(513);
+        let write_key = WriteKey {
+            account_identifier: "account",
+            namespace_identifier: "namespace",
+            key: &key,
+            params: WriteKeyParams::default(),
+            body: WriteKeyBody::Value(b"value".to_vec()),
+        };
+        let responses = TestClients::new(server.url()).request(&write_key);
+        assert_eq!(responses.len(), CLIENTS);
+        assert!(responses.iter().all(|response| matches!(
+            response,
+            Err(ApiFailure::InvalidEndpoint(RequestError::Invalid {
+                field: "key",
+                ..
+            }))
+        )));
+
+        let unserializable = DummyUnserializableEndpoint {
+            body: [((1, 2), 3)].into(),
+        };
+        let responses = TestClients::new(server.url()).request(&unserializable);
+        assert_eq!(responses.len(), CLIENTS);
+        assert!(responses.iter().all(|response| matches!(
+            response,
+            Err(ApiFailure::InvalidEndpoint(RequestError::Serialize(_)))
+        )));
+
+        mock.assert();
+    }
+
+    /// Test that both clients check the content type of raw responses, and ignore empty ones.
+    #[test]
+    fn test_clients_check_response_kinds() {
+        let mut server = Server::new();
+        let raw_mock = server
+            .mock("GET", "/dummy/raw")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"success": true}"#)
+            .expect(CLIENTS)
+            .create();
+        let empty_mock = server
+            .mock("DELETE", "/dummy/empty")
+            .with_status(204)
+            .expect(CLIENTS)
+            .create();
+
+        let responses = TestClients::new(server.url()).request(&DummyRawEndpoint);
+        raw_mock.assert();
+        assert_eq!(responses.len(), CLIENTS);
+        for response in responses {
+            assert_eq!(
+                response.unwrap_err(),
+                ApiFailure::UnexpectedContentType {
+                    expected: "application/octet-stream",
+                    received: Some("application/json".into()),
+                }
+            );
+        }
+
+        let responses = TestClients::new(server.url()).request(&DummyEmptyEndpoint);
+        empty_mock.assert();
+        assert_eq!(responses.len(), CLIENTS);
+        assert!(responses.into_iter().all(|response| response.is_ok()));
+    }
+
+    /// Test that both clients stream the body of raw responses, and parse the errors.
+    #[test]
+    fn test_clients_stream_raw_responses() {
+        let value = vec![7; 256 * 1024];
+
+        let mut server = Server::new();
+        let mock = server
+            .mock("GET", "/dummy/raw")
+            .with_status(200)
+            .with_header("content-type", "application/octet-stream")
+            .with_header("expiration", "1700000000")
+            .with_body(&value)
+            .expect(CLIENTS)
+            .create();
+
+        let clients = TestClients::new(server.url());
+        let runtime = &clients.runtime;
+        let response = runtime
+            .block_on(clients.client.request_stream(&DummyRawEndpoint))
+            .unwrap();
+        assert_eq!(response.headers["expiration"], "1700000000");
+        let body = runtime
+            .block_on(response.result.try_collect::<Vec<_>>())
+            .unwrap()
+            .concat();
+        assert_eq!(body, value);
+
+        #[cfg(feature = "blocking")]
+        {
+            use std::io::Read;
+
+            let mut response = clients.blocking.request_stream(&DummyRawEndpoint).unwrap();
+            assert_eq!(response.headers["expiration"], "1700000000");
+            let mut body = Vec::new();
+            response.result.read_to_end(&mut body).unwrap();
+            assert_eq!(body, value);
+        }
+        mock.assert();
+
+        let error =
+            json!({"success": false, "errors": [{"code": 10009, "message": "key not found"}]});
+        let mock = server
+            .mock("GET", "/dummy/raw")
+            .with_status(404)
+            .with_body(error.to_string())
+            .expect(CLIENTS)
+            .create();
+
+        let failure = runtime
+            .block_on(clients.client.request_stream(&DummyRawEndpoint))
+            .err()
+            .unwrap();
+        assert!(failure.has_error_code(ErrorCode::KvKeyNotFound));
+        #[cfg(feature = "blocking")]
+        {
+            let failure = clients
+                .blocking
+                .request_stream(&DummyRawEndpoint)
+                .err()
+                .unwrap();
+            assert!(failure.has_error_code(ErrorCode::KvKeyNotFound));
+        }
+        mock.assert();
+    }
+
+    /// Test that both clients return the status and headers along with the result.
+    #[test]
+    fn test_clients_return_response_meta() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("GET", "/dummy/raw")
+            .with_status(200)
+            .with_header("content-type", "application/octet-stream")
+            .with_header("expiration", "1700000000")
+            .with_header("cf-ray", "8c1c7bbd7d3f3b1a-CDG")
+            .with_body("value")
+            .expect(CLIENTS)
+            .create();
+
+        let responses = TestClients::new(server.url()).request_meta(&DummyRawEndpoint);
+
+        mock.assert();
+        assert_eq!(responses.len(), CLIENTS);
+        for response in responses {
+            let response = response.unwrap();
+            assert_eq!(response.status, http::StatusCode::OK);
+            assert_eq!(response.headers["expiration"], "1700000000");
+            assert_eq!(response.cf_ray(), Some("8c1c7bbd7d3f3b1a-CDG"));
+            assert_eq!(response.into_result(), b"value");
+        }
+    }
+
+    /// Test that both clients ask their credentials provider for credentials before each request.
+    #[test]
+    fn test_clients_use_credentials_providers() {
+        struct RotatingProvider(Mutex<u32>);
+
+        impl CredentialsProvider for RotatingProvider {
+            fn credentials(&self) -> Result<Credentials, CredentialsError> {
+                let mut count = self.0.lock().unwrap();
+                *count += 1;
+                match *count {
+                    1 => Err(CredentialsError::NotFound("no token yet".into())),
+                    count => Ok(Credentials::UserAuthToken {
+                        token: format!("token-{count}").into(),
+                    }),
+                }
+            }
+        }
+
+        let mut server = Server::new();
+        let mock = server
+            .mock("GET", "/dummy/nothing")
+            .match_header("authorization", "Bearer token-2")
+            .with_status(200)
+            .with_body(r#"{"result": null, "success": true}"#)
+            .expect(CLIENTS)
             .create();
 
-        let client = create_test_client(server.url());
-        let _ = client.request(&DummyJsonRequestWithQueryEndpoint).await;
+        let provider = || RotatingProvider(Mutex::new(0));
+        let clients = TestClients::with_credentials(server.url(), provider, ClientConfig::default);
+        let first = clients.request(&DummyNothingEndpoint);
+        let second = clients.request(&DummyNothingEndpoint);
 
         mock.assert();
+        assert!(first.iter().all(|response| matches!(
+            response,
+            Err(ApiFailure::Credentials(CredentialsError::NotFound(_)))
+        )));
+        assert!(second.iter().all(Result::is_ok));
     }
 }
@@ -0,0 +1,197 @@
+//! Running many independent requests, see `Client::batch`.
+use crate::framework::client::options::RequestOptions;
+use crate::framework::response::{ApiResponse, ErrorCode};
+use futures_util::stream::{Stream, StreamExt};
+use std::collections::HashMap;
+
+/// How `Client::batch` runs its requests.
+#[derive(Clone, Debug)]
+pub struct BatchOptions {
+    /// The maximum number of requests in flight at once. At least one request is sent.
+    ///
+    /// The client's rate limiter, if any, still applies to every request.
+    pub concurrency: usize,
+    /// The order in which the results are yielded.
+    pub order: BatchOrder,
+    /// The options of every request, e.g. a cancellation token which stops the whole batch.
+    pub request: RequestOptions,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        BatchOptions {
+            concurrency: 8,
+            order: BatchOrder::Input,
+            request: RequestOptions::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatchOrder {
+    /// In the order of the endpoints. A slow request holds back the results of the requests
+    /// after it, but not the requests themselves, within the concurrency limit.
+    Input,
+    /// As soon as each request completes.
+    Completion,
+}
+
+/// The result of a request of a batch.
+#[derive(Debug)]
+pub struct BatchItem<Endpoint, T> {
+    /// The position of the endpoint in the batch.
+    pub index: usize,
+    pub endpoint: Endpoint,
+    pub result: ApiResponse<T>,
+}
+
+/// Counts the successes and failures of a batch.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    pub succeeded: usize,
+    pub failed: usize,
+    /// The number of failures by the first error code returned by the API. Failures without an
+    /// error code, e.g. because the request could not be sent, are counted under `None`.
+    pub failures_by_code: HashMap<Option<ErrorCode>, usize>,
+}
+
+impl BatchSummary {
+    pub fn record<T>(&mut self, result: &ApiResponse<T>) {
+        match result {
+            Ok(_) => self.succeeded += 1,
+            Err(failure) => {
+                self.failed += 1;
+                let code = failure.error_codes().next();
+                *self.failures_by_code.entry(code).or_default() += 1;
+            }
+        }
+    }
+
+    /// Collects the items of a batch, along with their summary.
+    pub async fn collect<Endpoint, T>(
+        batch: impl Stream<Item = BatchItem<Endpoint, T>>,
+    ) -> (Vec<BatchItem<Endpoint, T>>, BatchSummary) {
+        batch
+            .fold(
+                (Vec::new(), BatchSummary::default()),
+                |(mut items, mut summary), item| async move {
+                    summary.record(&item.result);
+                    items.push(item);
+                    (items, summary)
+                },
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framework::auth::Credentials;
+    use crate::framework::client::async_api::Client;
+    use crate::framework::client::transport::{Transport, TransportFuture};
+    use crate::framework::client::ClientConfig;
+    use crate::framework::endpoint::EndpointSpec;
+    use crate::framework::response::ApiSuccess;
+    use crate::framework::Environment;
+    use bytes::Bytes;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    /// Answers after a delay which is shorter for later endpoints of a batch, with a "not found"
+    /// error for every third endpoint, and records how many requests are in flight at once.
+    #[derive(Clone, Default)]
+    struct BatchTransport {
+        in_flight: Arc<AtomicU32>,
+        max_in_flight: Arc<AtomicU32>,
+    }
+
+    impl Transport for BatchTransport {
+        fn send(&self, request: http::Request<Bytes>) -> TransportFuture<'_> {
+            Box::pin(async move {
+                let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+                let index: u64 = request
+                    .uri()
+                    .path()
+                    .rsplit('/')
+                    .next()
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                futures_timer::Delay::new(std::time::Duration::from_millis(5 * (10 - index))).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                let (status, body) = match index % 3 {
+                    0 => (
+                        404,
+                        r#"{"errors": [{"code": 10009, "message": "not found"}]}"#,
+                    ),
+                    _ => (200, r#"{"result": null, "success": true}"#),
+                };
+                Ok(http::Response::builder()
+                    .status(status)
+                    .header("content-type", "application/json")
+                    .body(Bytes::from_static(body.as_bytes()))
+                    .unwrap())
+            })
+        }
+    }
+
+    #[derive(Debug)]
+    struct DummyBatchEndpoint(u64);
+
+    impl EndpointSpec for DummyBatchEndpoint {
+        type JsonResponse = ();
+        type ResponseType = ApiSuccess<()>;
+
+        fn method(&self) -> http::Method {
+            http::Method::GET
+        }
+        fn path(&self) -> String {
+            format!("/dummy/batch/{}", self.0)
+        }
+    }
+
+    /// Test that batches run their requests within the concurrency limit, yield their results in
+    /// the requested order, and are summarized by error code.
+    #[tokio::test]
+    async fn test_batch() {
+        for order in [BatchOrder::Input, BatchOrder::Completion] {
+            let transport = BatchTransport::default();
+            let client = Client::new_with_transport(
+                transport.clone(),
+                Credentials::UserAuthToken {
+                    token: "dummy".into(),
+                },
+                ClientConfig::default(),
+                Environment::Custom("https://example.com/".into()),
+            );
+            let options = BatchOptions {
+                concurrency: 3,
+                order,
+                ..BatchOptions::default()
+            };
+
+            let batch = client.batch((0..10).map(DummyBatchEndpoint), options);
+            let (items, summary) = BatchSummary::collect(batch).await;
+
+            assert_eq!(transport.max_in_flight.load(Ordering::SeqCst), 3);
+            let indexes: Vec<usize> = items.iter().map(|item| item.index).collect();
+            let mut sorted = indexes.clone();
+            sorted.sort();
+            assert_eq!(sorted, (0..10).collect::<Vec<_>>());
+            // Later requests complete first.
+            assert_eq!(indexes == sorted, order == BatchOrder::Input);
+            assert!(items
+                .iter()
+                .all(|item| item.endpoint.0 as usize == item.index));
+
+            assert_eq!(summary.succeeded, 6);
+            assert_eq!(summary.failed, 4);
+            assert_eq!(
+                summary.failures_by_code,
+                [(Some(ErrorCode::from(10009)), 4)].into()
+            );
+        }
+    }
+}
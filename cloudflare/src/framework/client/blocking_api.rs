@@ -1,20 +1,39 @@
-use crate::framework::auth::Credentials;
-use crate::framework::client::ClientConfig;
-use crate::framework::endpoint::{EndpointSpec, MultipartPart, RequestBody};
-use crate::framework::response::{
-    ApiErrors, ApiFailure, ApiResponse, ApiSuccess, ResponseConverter,
+use crate::framework::auth::{Credentials, CredentialsProvider};
+use crate::framework::client::codec;
+use crate::framework::client::middleware::{Middleware, ReceivedBody};
+use crate::framework::client::options::{CancellationToken, RequestOptions};
+use crate::framework::client::rate_limit::RateLimiter;
+use crate::framework::client::retry::{Attempts, RetryPolicy, Step};
+use crate::framework::client::trace::RequestSpan;
+use crate::framework::client::transport::{
+    BlockingTransport, BodyReader, ReqwestBlockingTransport, TransportError, TransportErrorKind,
 };
+use crate::framework::client::{BlockingApiClient, ClientConfig};
+use crate::framework::endpoint::{EndpointSpec, Paginated};
+use crate::framework::response::{ApiFailure, ApiResponse, ApiSuccess, Response};
 use crate::framework::{auth::AuthClient, Environment};
+use bytes::Bytes;
 use reqwest::blocking::RequestBuilder;
-use std::borrow::Cow;
+use std::io::Read;
 use std::net::SocketAddr;
+use std::sync::Arc;
 
 /// Synchronous Cloudflare API client.
+///
+/// The client is given `Credentials`, or any `CredentialsProvider`, which it asks for credentials
+/// before each request. Requests whose credentials are rejected are retried once with refreshed
+/// credentials, see `CredentialsProvider::refresh`.
+///
+/// Clones share the same transport (and thus connection pool) and rate limiter.
 // TODO: Rename to BlockingClient?
+#[derive(Clone)]
 pub struct HttpApiClient {
     environment: Environment,
-    credentials: Credentials,
-    http_client: reqwest::blocking::Client,
+    credentials: Arc<dyn CredentialsProvider>,
+    transport: Arc<dyn BlockingTransport>,
+    retry_policy: Option<RetryPolicy>,
+    rate_limiter: Option<RateLimiter>,
+    middleware: Vec<Arc<dyn Middleware>>,
 }
 
 impl HttpApiClient {
@@ -27,7 +46,7 @@ impl HttpApiClient {
 
 impl HttpApiClient {
     pub fn new(
-        credentials: Credentials,
+        credentials: impl CredentialsProvider + 'static,
         config: ClientConfig,
         environment: Environment,
     ) -> Result<HttpApiClient, crate::framework::Error> {
@@ -36,7 +55,7 @@ impl HttpApiClient {
             .default_headers(config.default_headers);
 
         if let Some(address) = config.resolve_ip {
-            let url = url::Url::from(&environment);
+            let url = url::Url::try_from(&environment)?;
             builder = builder.resolve(
                 url.host_str()
                     .expect("Environment url should have a hostname"),
@@ -47,21 +66,50 @@ impl HttpApiClient {
 
         Ok(HttpApiClient {
             environment,
-            credentials,
-            http_client,
+            credentials: Arc::new(credentials),
+            transport: Arc::new(ReqwestBlockingTransport::new(http_client)),
+            retry_policy: config.retry_policy,
+            rate_limiter: config.rate_limit.as_ref().map(RateLimiter::new),
+            middleware: config.middleware,
         })
     }
 
+    /// Creates a client which sends its requests with the given `reqwest` client.
+    ///
+    /// Only the retry policy, rate limit and middleware of the config are used. The other settings
+    /// (timeout, default headers, ...) are those of `client`.
     pub fn new_with_client(
         client: reqwest::blocking::Client,
-        credentials: Credentials,
+        credentials: impl CredentialsProvider + 'static,
+        config: ClientConfig,
         environment: Environment,
     ) -> Result<HttpApiClient, crate::framework::Error> {
-        Ok(HttpApiClient {
-            environment,
+        Ok(HttpApiClient::new_with_transport(
+            ReqwestBlockingTransport::new(client),
             credentials,
-            http_client: client,
-        })
+            config,
+            environment,
+        ))
+    }
+
+    /// Creates a client which sends its requests through the given transport.
+    ///
+    /// Only the retry policy, rate limit and middleware of the config are used. The other settings
+    /// (timeout, default headers, ...) are up to the transport.
+    pub fn new_with_transport(
+        transport: impl BlockingTransport + 'static,
+        credentials: impl CredentialsProvider + 'static,
+        config: ClientConfig,
+        environment: Environment,
+    ) -> HttpApiClient {
+        HttpApiClient {
+            environment,
+            credentials: Arc::new(credentials),
+            transport: Arc::new(transport),
+            retry_policy: config.retry_policy,
+            rate_limiter: config.rate_limit.as_ref().map(RateLimiter::new),
+            middleware: config.middleware,
+        }
     }
 
     //noinspection ALL
@@ -70,113 +118,237 @@ impl HttpApiClient {
     pub fn request<Endpoint>(&self, endpoint: &Endpoint) -> ApiResponse<Endpoint::ResponseType>
     where
         Endpoint: EndpointSpec + Send + Sync,
-        Endpoint::ResponseType: ResponseConverter<Endpoint::JsonResponse>,
     {
-        // Build the request
-        let mut request = self
-            .http_client
-            .request(endpoint.method(), endpoint.url(&self.environment));
-
-        if let Some(body) = endpoint.body() {
-            match body {
-                RequestBody::Json(json) => {
-                    request = request.body(json);
-                }
-                RequestBody::Raw(bytes) => {
-                    request = request.body(bytes);
+        self.request_with_meta(endpoint).map(Response::into_result)
+    }
+
+    /// Synchronously send a request to the Cloudflare API, with options which only apply to this
+    /// request, e.g. a longer timeout.
+    pub fn request_with<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+        options: &RequestOptions,
+    ) -> ApiResponse<Endpoint::ResponseType>
+    where
+        Endpoint: EndpointSpec + Send + Sync,
+    {
+        self.request_with_options(endpoint, options)
+            .map(Response::into_result)
+    }
+
+    /// Synchronously send a request to the Cloudflare API, and return its result along with the
+    /// status and headers of the response.
+    pub fn request_with_meta<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+    ) -> ApiResponse<Response<Endpoint::ResponseType>>
+    where
+        Endpoint: EndpointSpec + Send + Sync,
+    {
+        self.request_with_options(endpoint, &RequestOptions::default())
+    }
+
+    fn request_with_options<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+        options: &RequestOptions,
+    ) -> ApiResponse<Response<Endpoint::ResponseType>>
+    where
+        Endpoint: EndpointSpec + Send + Sync,
+    {
+        let span = RequestSpan::new(endpoint);
+        span.in_scope(|| {
+            let (mut request, upload) =
+                codec::build_request(endpoint, &self.environment, &*self.credentials)?;
+            codec::apply_options(&mut request, options);
+            let response = match upload {
+                None => self.send(self.attempts(request), |request| {
+                    self.transport.send(request)
+                })?,
+                // A streamed body can only be read once, so the request is not retried.
+                Some(body) => {
+                    let mut body = Some(body);
+                    self.send(self.attempts(request).once(), |request| {
+                        let body = body.take().expect("the request is only sent once");
+                        self.transport.send_upload(request, body)
+                    })?
                 }
-                RequestBody::MultiPart(multipart) => {
-                    let mut form = reqwest::blocking::multipart::Form::new();
-                    for (name, part) in multipart.parts() {
-                        match part {
-                            MultipartPart::Text(text) => {
-                                form = form.text(name, text);
-                            }
-                            MultipartPart::Bytes(bytes) => {
-                                form = form
-                                    .part(name, reqwest::blocking::multipart::Part::bytes(bytes));
-                            }
-                        }
+            };
+            span.record_response(&response);
+            let (status, headers) = (response.status(), response.headers().clone());
+            let result = codec::parse_response(endpoint, &self.environment, response)?;
+            Ok(Response {
+                status,
+                headers,
+                result,
+            })
+        })
+    }
+
+    /// Fetches every page of a list endpoint, one after the other, and iterates over the listed
+    /// items.
+    ///
+    /// Pages are only requested once the items of the previous page have been consumed.
+    /// The iterator ends after the first error.
+    pub fn paginate<'a, Endpoint>(
+        &'a self,
+        endpoint: Endpoint,
+    ) -> impl Iterator<Item = ApiResponse<<Endpoint::JsonResponse as IntoIterator>::Item>> + 'a
+    where
+        Endpoint: Paginated
+            + EndpointSpec<ResponseType = ApiSuccess<<Endpoint as EndpointSpec>::JsonResponse>>
+            + Send
+            + Sync
+            + 'a,
+        Endpoint::JsonResponse: IntoIterator,
+    {
+        let mut next = Some(endpoint);
+        let mut items: Option<<Endpoint::JsonResponse as IntoIterator>::IntoIter> = None;
+        std::iter::from_fn(move || loop {
+            if let Some(item) = items.as_mut().and_then(Iterator::next) {
+                return Some(Ok(item));
+            }
+            let mut endpoint = next.take()?;
+            match self.request(&endpoint) {
+                Ok(page) => {
+                    let has_next = page
+                        .result_info
+                        .as_ref()
+                        .is_some_and(|result_info| endpoint.next_page(result_info));
+                    if has_next {
+                        next = Some(endpoint);
                     }
-                    request = request.multipart(form);
+                    items = Some(page.result.into_iter());
                 }
+                Err(error) => return Some(Err(error)),
             }
-            // Reqwest::RequestBuilder::multipart sets the content type for us.
-            match endpoint.content_type() {
-                None | Some(Cow::Borrowed("multipart/form-data")) => {}
-                Some(content_type) => {
-                    request = request.header(reqwest::header::CONTENT_TYPE, content_type.as_ref());
-                }
+        })
+    }
+
+    /// Synchronously send a request to an endpoint which returns raw bytes, and return a reader
+    /// of its body as it is received, instead of reading it into memory.
+    ///
+    /// The request is retried according to the client's retry policy until its response headers
+    /// are received, but errors which happen while reading the body are returned by the reader.
+    /// A streamed request body is read into memory first.
+    pub fn request_stream<Endpoint>(&self, endpoint: &Endpoint) -> ApiResponse<Response<BodyReader>>
+    where
+        Endpoint: EndpointSpec<ResponseType = Vec<u8>> + Send + Sync,
+    {
+        let span = RequestSpan::new(endpoint);
+        span.in_scope(|| {
+            let (mut request, upload) =
+                codec::build_request(endpoint, &self.environment, &*self.credentials)?;
+            if let Some(body) = upload {
+                let mut buffer = Vec::new();
+                body.into_reader()
+                    .read_to_end(&mut buffer)
+                    .map_err(|error| TransportError::new(TransportErrorKind::Other, error))?;
+                *request.body_mut() = Bytes::from(buffer);
             }
-        }
+            let response = self.send(self.attempts(request), |request| {
+                self.transport.send_streaming(request)
+            })?;
+            span.record_head(&response);
+            let (parts, mut body) = response.into_parts();
+            if !parts.status.is_success() {
+                let mut buffer = Vec::new();
+                body.read_to_end(&mut buffer)
+                    .map_err(|error| TransportError::new(TransportErrorKind::Other, error))?;
+                return Err(codec::parse_failure(
+                    endpoint,
+                    &self.environment,
+                    parts,
+                    &buffer,
+                ));
+            }
+            codec::check_content_type::<Vec<u8>>(&parts.headers)?;
+            Ok(Response {
+                status: parts.status,
+                headers: parts.headers,
+                result: body,
+            })
+        })
+    }
 
-        request = request.auth(&self.credentials);
-        let response = request.send()?;
-
-        // The condition is necessary, even if a warning is present.
-        // The constant is overridden in some cases.
-        if Endpoint::IS_RAW_BODY {
-            let content_type = response
-                .headers()
-                .get(reqwest::header::CONTENT_TYPE)
-                .and_then(|ct| ct.to_str().ok())
-                .unwrap_or("");
-            assert_eq!(content_type, "application/octet-stream");
-
-            map_api_response_raw::<Endpoint>(response)
-        } else {
-            map_api_response_json::<Endpoint>(response)
-        }
+    /// The attempts at sending `request` with the retry policy, rate limiter, middleware and
+    /// credentials of the client.
+    fn attempts(&self, request: http::Request<Bytes>) -> Attempts<'_> {
+        Attempts::new(
+            request,
+            self.retry_policy.as_ref(),
+            self.rate_limiter.as_ref(),
+            &self.middleware,
+            &*self.credentials,
+        )
     }
-}
 
-impl AuthClient for RequestBuilder {
-    fn auth(mut self, credentials: &Credentials) -> Self {
-        for (k, v) in credentials.headers() {
-            self = self.header(k, v);
+    /// Sends the request of `attempts` through `transport`, waiting and retrying as they decide,
+    /// unless the request was cancelled.
+    fn send<B: ReceivedBody>(
+        &self,
+        mut attempts: Attempts<'_>,
+        mut transport: impl FnMut(http::Request<Bytes>) -> Result<http::Response<B>, TransportError>,
+    ) -> ApiResponse<http::Response<B>> {
+        let mut step = attempts.start();
+        loop {
+            step = match step {
+                Step::Wait(delay) => {
+                    std::thread::sleep(delay);
+                    attempts.resume()
+                }
+                Step::Send(request) => {
+                    let token = request.extensions().get::<CancellationToken>();
+                    if token.is_some_and(CancellationToken::is_cancelled) {
+                        return Err(ApiFailure::Cancelled);
+                    }
+                    attempts.received(transport(request))
+                }
+                Step::Done(result) => return result,
+            };
         }
-        self
     }
 }
 
-// If the response is 2XX and parses, return Success.
-// If the response is 2XX and doesn't parse, return Invalid.
-// If the response isn't 2XX, return Failure, with API errors if they were included.
-fn map_api_response_raw<Endpoint>(
-    resp: reqwest::blocking::Response,
-) -> Result<Endpoint::ResponseType, ApiFailure>
-where
-    Endpoint: EndpointSpec,
-    Endpoint::ResponseType: ResponseConverter<Endpoint::JsonResponse>,
-{
-    let status = resp.status();
-    if status.is_success() {
-        let bytes = resp.bytes().map_err(ApiFailure::Invalid)?.to_vec();
-        Ok(Endpoint::ResponseType::from_raw(bytes))
-    } else {
-        let parsed: Result<ApiErrors, reqwest::Error> = resp.json();
-        let errors = parsed.unwrap_or_default();
-        Err(ApiFailure::Error(status, errors))
+impl BlockingApiClient for HttpApiClient {
+    fn request<Endpoint>(&self, endpoint: &Endpoint) -> ApiResponse<Endpoint::ResponseType>
+    where
+        Endpoint: EndpointSpec + Send + Sync,
+    {
+        HttpApiClient::request(self, endpoint)
+    }
+
+    fn request_with<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+        options: &RequestOptions,
+    ) -> ApiResponse<Endpoint::ResponseType>
+    where
+        Endpoint: EndpointSpec + Send + Sync,
+    {
+        HttpApiClient::request_with(self, endpoint, options)
+    }
+
+    fn request_with_meta<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+    ) -> ApiResponse<Response<Endpoint::ResponseType>>
+    where
+        Endpoint: EndpointSpec + Send + Sync,
+    {
+        HttpApiClient::request_with_meta(self, endpoint)
     }
 }
 
-fn map_api_response_json<Endpoint>(
-    resp: reqwest::blocking::Response,
-) -> Result<Endpoint::ResponseType, ApiFailure>
-where
-    Endpoint: EndpointSpec,
-    Endpoint::ResponseType: ResponseConverter<Endpoint::JsonResponse>,
-{
-    let status = resp.status();
-    if status.is_success() {
-        let parsed: Result<ApiSuccess<Endpoint::JsonResponse>, reqwest::Error> = resp.json();
-        match parsed {
-            Ok(success) => Ok(Endpoint::ResponseType::from_json(success)),
-            Err(e) => Err(ApiFailure::Invalid(e)),
+impl AuthClient for RequestBuilder {
+    fn auth(mut self, credentials: &Credentials) -> Self {
+        for (name, value) in credentials.headers() {
+            self = match codec::sensitive_header(&value) {
+                Ok(value) => self.header(name, value),
+                // The builder reports the invalid value when the request is built.
+                Err(_) => self.header(name, value.expose_secret().as_str()),
+            };
         }
-    } else {
-        let parsed: Result<ApiErrors, reqwest::Error> = resp.json();
-        let errors = parsed.unwrap_or_default();
-        Err(ApiFailure::Error(status, errors))
+        self
     }
 }
@@ -0,0 +1,374 @@
+//! Dry runs, which render the requests of a client instead of sending them, see
+//! [`DryRunTransport`]. A single endpoint can also be rendered as a `curl` command, see
+//! [`to_curl`].
+use crate::framework::auth::Credentials;
+use crate::framework::client::codec::{self, redact_url, CREDENTIAL_HEADERS, REDACTED};
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+use crate::framework::client::transport::{BlockingTransport, TransportError};
+use crate::framework::client::transport::{Transport, TransportFuture};
+use crate::framework::endpoint::EndpointSpec;
+use crate::framework::response::ApiFailure;
+use crate::framework::Environment;
+use bytes::Bytes;
+use http::header::{HeaderValue, CONTENT_TYPE};
+use http::StatusCode;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+/// A request as it would be sent, with its credentials and the secrets of its body redacted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenderedRequest {
+    pub method: http::Method,
+    /// The URL of the request, with secrets in its user info and query string redacted.
+    pub url: String,
+    /// The headers of the request, with credentials redacted.
+    pub headers: http::HeaderMap,
+    /// The body of the request. The fields of JSON bodies which hold a `Secret`, such as the
+    /// `text` of a Worker secret or a `tunnel_secret`, are redacted.
+    pub body: Vec<u8>,
+}
+
+/// The fields of JSON bodies which hold a `Secret`, whatever the endpoint.
+const SECRET_FIELDS: &[&str] = &["custom_key", "tunnel_secret"];
+
+impl RenderedRequest {
+    pub fn new(request: &http::Request<impl AsRef<[u8]>>) -> RenderedRequest {
+        let mut headers = request.headers().clone();
+        for (name, value) in headers.iter_mut() {
+            if value.is_sensitive() || CREDENTIAL_HEADERS.contains(&name.as_str()) {
+                *value = HeaderValue::from_static(REDACTED);
+            }
+        }
+        RenderedRequest {
+            method: request.method().clone(),
+            url: redact_url(&request.uri().to_string()),
+            body: redact_body(&headers, request.body().as_ref()),
+            headers,
+        }
+    }
+
+    /// Renders the request as an equivalent `curl` command line, for a POSIX shell.
+    ///
+    /// Bodies which are not UTF-8, such as multipart bodies with binary files, are quoted as
+    /// `$'...'`, which is understood by bash and zsh.
+    pub fn to_curl(&self) -> String {
+        let mut lines = vec![format!("curl -X {} {}", self.method, quote(&self.url))];
+        for (name, value) in &self.headers {
+            let header = format!("{name}: {}", String::from_utf8_lossy(value.as_bytes()));
+            lines.push(format!("-H {}", quote(&header)));
+        }
+        if !self.body.is_empty() {
+            let body = match std::str::from_utf8(&self.body) {
+                Ok(body) => quote(body),
+                Err(_) => quote_bytes(&self.body),
+            };
+            lines.push(format!("--data-binary {body}"));
+        }
+        lines.join(" \\\n  ")
+    }
+}
+
+/// Renders the request for an endpoint as an equivalent `curl` command line, with its credentials
+/// redacted, e.g. for a support ticket. See `RenderedRequest::to_curl`.
+pub fn to_curl<Endpoint>(
+    endpoint: &Endpoint,
+    environment: &Environment,
+    credentials: &Credentials,
+) -> Result<String, ApiFailure>
+where
+    Endpoint: EndpointSpec,
+{
+    let request = codec::build_http_request(endpoint, environment, credentials)?;
+    Ok(RenderedRequest::new(&request).to_curl())
+}
+
+/// Redacts the secrets of a JSON body. Other bodies, and JSON bodies without secrets, are kept
+/// as they are.
+fn redact_body(headers: &http::HeaderMap, body: &[u8]) -> Vec<u8> {
+    let is_json = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !is_json {
+        return body.to_vec();
+    }
+    let Ok(mut json) = serde_json::from_slice::<serde_json::Value>(body) else {
+        return body.to_vec();
+    };
+    if !redact_json(&mut json) {
+        return body.to_vec();
+    }
+    serde_json::to_vec(&json).unwrap_or_else(|_| body.to_vec())
+}
+
+/// Replaces the secrets of a JSON value, and returns whether there were any.
+fn redact_json(value: &mut serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Object(object) => {
+            // Worker secrets and secret bindings hold their value in `text`.
+            let is_secret_text = object.get("type").is_some_and(|kind| kind == "secret_text");
+            let mut redacted = false;
+            for (key, value) in object.iter_mut() {
+                if SECRET_FIELDS.contains(&key.as_str()) || (is_secret_text && key == "text") {
+                    *value = REDACTED.into();
+                    redacted = true;
+                } else {
+                    redacted |= redact_json(value);
+                }
+            }
+            redacted
+        }
+        serde_json::Value::Array(values) => values
+            .iter_mut()
+            .fold(false, |redacted, value| redact_json(value) | redacted),
+        _ => false,
+    }
+}
+
+/// Quotes a shell word, in single quotes.
+fn quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
+
+/// Quotes bytes as an ANSI-C string (`$'...'`), escaping all but printable ASCII.
+fn quote_bytes(bytes: &[u8]) -> String {
+    let mut quoted = String::from("$'");
+    for &byte in bytes {
+        match byte {
+            b'\\' | b'\'' => {
+                quoted.push('\\');
+                quoted.push(byte as char);
+            }
+            b' '..=b'~' => quoted.push(byte as char),
+            _ => {
+                let _ = write!(quoted, "\\x{byte:02x}");
+            }
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// A transport which records the requests of a client instead of sending them, and answers each
+/// of them with the same synthetic response.
+///
+/// By default, the response is a `200 OK` whose result is `null`. Endpoints whose result can't be
+/// `null` fail to parse it, after their request was recorded; `with_response` can give them a
+/// result they can parse. The recording shares its state with its clones, so a clone can be kept
+/// to read the requests once the client is done:
+///
+/// ```no_run
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// use cloudflare::endpoints::zones::zone::DeleteZone;
+/// use cloudflare::framework::auth::Credentials;
+/// use cloudflare::framework::client::async_api::Client;
+/// use cloudflare::framework::client::dry_run::DryRunTransport;
+/// use cloudflare::framework::client::ClientConfig;
+/// use cloudflare::framework::Environment;
+///
+/// let dry_run = DryRunTransport::new()
+///     .with_response(http::StatusCode::OK, r#"{"result": {"id": "dry-run"}, "success": true}"#);
+/// let credentials = Credentials::UserAuthToken { token: "secret".into() };
+/// let client = Client::new_with_transport(
+///     dry_run.clone(),
+///     credentials,
+///     ClientConfig::default(),
+///     Environment::Production,
+/// );
+/// client.request(&DeleteZone { zone_identifier: "023e105f4ecef8ad9ca31a8372d0c353" }).await?;
+/// for request in dry_run.requests() {
+///     println!("{}", request.to_curl());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct DryRunTransport {
+    requests: Arc<Mutex<Vec<RenderedRequest>>>,
+    status: StatusCode,
+    body: Bytes,
+}
+
+impl Default for DryRunTransport {
+    fn default() -> Self {
+        DryRunTransport {
+            requests: Default::default(),
+            status: StatusCode::OK,
+            body: Bytes::from_static(
+                br#"{"result": null, "success": true, "errors": [], "messages": []}"#,
+            ),
+        }
+    }
+}
+
+impl DryRunTransport {
+    pub fn new() -> Self {
+        DryRunTransport::default()
+    }
+
+    /// Answers requests with `status` and the JSON `body`, instead of the default response.
+    pub fn with_response(mut self, status: StatusCode, body: impl Into<Bytes>) -> Self {
+        self.status = status;
+        self.body = body.into();
+        self
+    }
+
+    /// The requests recorded so far, in the order they were sent.
+    pub fn requests(&self) -> Vec<RenderedRequest> {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<RenderedRequest>> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn respond(&self, request: &http::Request<Bytes>) -> http::Response<Bytes> {
+        self.lock().push(RenderedRequest::new(request));
+        let mut response = http::Response::new(self.body.clone());
+        *response.status_mut() = self.status;
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        response
+    }
+}
+
+impl Transport for DryRunTransport {
+    fn send(&self, request: http::Request<Bytes>) -> TransportFuture<'_> {
+        let response = self.respond(&request);
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+impl BlockingTransport for DryRunTransport {
+    fn send(&self, request: http::Request<Bytes>) -> Result<http::Response<Bytes>, TransportError> {
+        Ok(self.respond(&request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoints::workers::{CreateSecret, CreateSecretParams};
+    use crate::endpoints::zones::zone::DeleteZone;
+    use crate::framework::client::async_api::Client;
+    use crate::framework::client::ClientConfig;
+
+    const ZONE: &str = "023e105f4ecef8ad9ca31a8372d0c353";
+
+    fn credentials() -> Credentials {
+        Credentials::UserAuthToken {
+            token: "secret".into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn records_requests_instead_of_sending_them() {
+        let dry_run = DryRunTransport::new().with_response(
+            StatusCode::OK,
+            r#"{"result": {"id": "dry-run"}, "success": true}"#,
+        );
+        let client = Client::new_with_transport(
+            dry_run.clone(),
+            credentials(),
+            ClientConfig::default(),
+            Environment::Production,
+        );
+        let endpoint = DeleteZone {
+            zone_identifier: ZONE,
+        };
+
+        let response = client.request(&endpoint).await.unwrap();
+        assert_eq!(response.result.id, "dry-run");
+
+        #[cfg(feature = "blocking")]
+        {
+            let client = crate::framework::client::blocking_api::HttpApiClient::new_with_transport(
+                dry_run.clone(),
+                credentials(),
+                ClientConfig::default(),
+                Environment::Production,
+            );
+            assert!(client.request(&endpoint).is_ok());
+        }
+
+        let requests = dry_run.requests();
+        assert_eq!(
+            requests.len(),
+            if cfg!(feature = "blocking") { 2 } else { 1 }
+        );
+        for request in requests {
+            assert_eq!(request.method, http::Method::DELETE);
+            assert_eq!(
+                request.url,
+                format!("https://api.cloudflare.com/client/v4/zones/{ZONE}")
+            );
+            assert_eq!(request.headers["authorization"], REDACTED);
+            assert!(request.body.is_empty());
+        }
+    }
+
+    #[test]
+    fn renders_curl_commands() {
+        let endpoint = DeleteZone {
+            zone_identifier: ZONE,
+        };
+        let curl = to_curl(&endpoint, &Environment::Production, &credentials()).unwrap();
+        assert_eq!(
+            curl,
+            format!(
+                "curl -X DELETE 'https://api.cloudflare.com/client/v4/zones/{ZONE}' \\\n  \
+                 -H 'authorization: [REDACTED]'"
+            )
+        );
+
+        let request = http::Request::post("https://example.com/?access_token=secret")
+            .header("content-type", "application/json")
+            .body(r#"{"name": "it's"}"#)
+            .unwrap();
+        assert_eq!(
+            RenderedRequest::new(&request).to_curl(),
+            "curl -X POST 'https://example.com/?access_token=%5BREDACTED%5D' \\\n  \
+             -H 'content-type: application/json' \\\n  \
+             --data-binary '{\"name\": \"it'\\''s\"}'"
+        );
+
+        let request = http::Request::put("https://example.com/")
+            .body(vec![b'a', b'\'', 0, 0xff])
+            .unwrap();
+        assert_eq!(
+            RenderedRequest::new(&request).to_curl(),
+            "curl -X PUT 'https://example.com/' \\\n  --data-binary $'a\\'\\x00\\xff'"
+        );
+    }
+
+    #[test]
+    fn redacts_secrets_in_bodies() {
+        let endpoint = CreateSecret {
+            account_identifier: "abc",
+            script_name: "worker",
+            params: CreateSecretParams {
+                name: "API_KEY".into(),
+                text: "hunter2".into(),
+                secret_type: "secret_text".into(),
+            },
+        };
+        let curl = to_curl(&endpoint, &Environment::Production, &credentials()).unwrap();
+        assert!(!curl.contains("hunter2"), "{curl}");
+        assert!(curl.contains(r#""text":"[REDACTED]""#), "{curl}");
+        assert!(curl.contains(r#""name":"API_KEY""#), "{curl}");
+
+        let request = http::Request::post("https://example.com/")
+            .header("content-type", "application/json")
+            .body(r#"{"ssl": {"custom_key": "private"}, "text": "public"}"#)
+            .unwrap();
+        let rendered = RenderedRequest::new(&request);
+        assert_eq!(
+            rendered.body,
+            br#"{"ssl":{"custom_key":"[REDACTED]"},"text":"public"}"#
+        );
+    }
+}
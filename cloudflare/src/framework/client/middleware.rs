@@ -0,0 +1,193 @@
+//! Hooks run by the clients around every request they send.
+//!
+//! Middleware is added to [`ClientConfig::middleware`](super::ClientConfig::middleware), e.g. to
+//! add headers, count requests or refuse to send some of them:
+//!
+//! ```
+//! use bytes::Bytes;
+//! use cloudflare::framework::client::middleware::{Middleware, MiddlewareError};
+//! use cloudflare::framework::client::ClientConfig;
+//! use std::sync::Arc;
+//!
+//! /// A read-only mode: refuses to send requests which could change anything.
+//! struct ReadOnly;
+//!
+//! impl Middleware for ReadOnly {
+//!     fn before_send(&self, request: &mut http::Request<Bytes>) -> Result<(), MiddlewareError> {
+//!         if request.method().is_safe() {
+//!             Ok(())
+//!         } else {
+//!             Err(format!("{} requests are not allowed", request.method()).into())
+//!         }
+//!     }
+//! }
+//!
+//! let config = ClientConfig {
+//!     middleware: vec![Arc::new(ReadOnly)],
+//!     ..ClientConfig::default()
+//! };
+//! ```
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+use crate::framework::client::transport::BodyReader;
+use crate::framework::client::transport::BodyStream;
+use crate::framework::response::ApiFailure;
+use bytes::Bytes;
+use std::error::Error;
+use std::sync::Arc;
+
+/// The error returned by a middleware to stop a request.
+pub type MiddlewareError = Box<dyn Error + Send + Sync>;
+
+/// A hook run before each request is sent, and after its response is received.
+///
+/// Both hooks run once per attempt: a request which is retried goes through the middleware
+/// again. `before_send` hooks run in the order of the chain, and `after_receive` hooks in the
+/// reverse order.
+pub trait Middleware: Send + Sync {
+    /// Called with the complete request (URL, headers including authentication, and body), which
+    /// may be modified.
+    ///
+    /// Returning an error stops the request before it is sent: the client then returns
+    /// `ApiFailure::Rejected`, and does not retry it.
+    fn before_send(&self, _request: &mut http::Request<Bytes>) -> Result<(), MiddlewareError> {
+        Ok(())
+    }
+
+    /// Called with the request as it was sent and its response, whatever its status, before the
+    /// response is parsed. Not called when no response was received.
+    ///
+    /// The body of streamed responses is not read in advance, so they are shown with an empty
+    /// body.
+    fn after_receive(&self, _request: &http::Request<Bytes>, _response: &http::Response<Bytes>) {}
+}
+
+/// Runs the `before_send` hooks of a chain, stopping at the first error.
+pub(crate) fn before_send(
+    chain: &[Arc<dyn Middleware>],
+    request: &mut http::Request<Bytes>,
+) -> Result<(), ApiFailure> {
+    for middleware in chain {
+        middleware
+            .before_send(request)
+            .map_err(ApiFailure::Rejected)?;
+    }
+    Ok(())
+}
+
+/// Runs the `after_receive` hooks of a chain.
+pub(crate) fn after_receive<B: ReceivedBody>(
+    chain: &[Arc<dyn Middleware>],
+    request: &http::Request<Bytes>,
+    response: &http::Response<B>,
+) {
+    B::inspect(response, |response| {
+        for middleware in chain.iter().rev() {
+            middleware.after_receive(request, response);
+        }
+    });
+}
+
+/// The body of a response received by a client, either read or streamed.
+pub(crate) trait ReceivedBody: Sized {
+    /// Calls `f` with the response, or with its status and headers only if it is streamed.
+    fn inspect(response: &http::Response<Self>, f: impl FnOnce(&http::Response<Bytes>));
+}
+
+impl ReceivedBody for Bytes {
+    fn inspect(response: &http::Response<Self>, f: impl FnOnce(&http::Response<Bytes>)) {
+        f(response)
+    }
+}
+
+impl ReceivedBody for BodyStream {
+    fn inspect(response: &http::Response<Self>, f: impl FnOnce(&http::Response<Bytes>)) {
+        f(&head(response))
+    }
+}
+
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+impl ReceivedBody for BodyReader {
+    fn inspect(response: &http::Response<Self>, f: impl FnOnce(&http::Response<Bytes>)) {
+        f(&head(response))
+    }
+}
+
+/// Copies the status and headers of a response, without its body.
+fn head<B>(response: &http::Response<B>) -> http::Response<Bytes> {
+    let mut head = http::Response::new(Bytes::new());
+    *head.status_mut() = response.status();
+    *head.version_mut() = response.version();
+    *head.headers_mut() = response.headers().clone();
+    head
+}
+
+// The middleware is tested around requests to a mock server with `reqwest`.
+#[cfg(all(test, feature = "reqwest"))]
+mod tests {
+    use super::*;
+    use crate::framework::client::test_support::*;
+    use crate::framework::client::ClientConfig;
+    use crate::framework::response::ApiFailure;
+    use mockito::Server;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    /// Adds a header to requests, refuses to send POST requests, and records response statuses.
+    #[derive(Default)]
+    struct TestMiddleware {
+        statuses: Mutex<Vec<(http::Method, u16)>>,
+    }
+
+    impl Middleware for TestMiddleware {
+        fn before_send(&self, request: &mut http::Request<Bytes>) -> Result<(), MiddlewareError> {
+            if request.method() == http::Method::POST {
+                return Err("read-only mode".into());
+            }
+            let tenant = http::HeaderValue::from_static("tenant-1");
+            request.headers_mut().insert("x-tenant", tenant);
+            Ok(())
+        }
+
+        fn after_receive(&self, request: &http::Request<Bytes>, response: &http::Response<Bytes>) {
+            let status = (request.method().clone(), response.status().as_u16());
+            self.statuses.lock().unwrap().push(status);
+        }
+    }
+
+    /// Test that both clients run the middleware around each request.
+    #[test]
+    fn test_clients_run_middleware() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("GET", "/dummy/json")
+            .match_header("x-tenant", "tenant-1")
+            .with_status(200)
+            .with_body(json!({"result": {"message": "Hello"}}).to_string())
+            .expect(CLIENTS)
+            .create();
+        let post = server.mock("POST", "/dummy/json").expect(0).create();
+
+        let middleware = Arc::new(TestMiddleware::default());
+        let config = || ClientConfig {
+            middleware: vec![middleware.clone()],
+            ..ClientConfig::default()
+        };
+        let clients = TestClients::with_config(server.url(), config);
+        let responses = clients.request(&DummyJsonEndpoint);
+        for response in responses {
+            assert_eq!(response.unwrap().result.message, "Hello");
+        }
+        let responses = clients.request(&DummyJsonRequestEndpoint);
+        for response in responses {
+            let Err(ApiFailure::Rejected(error)) = response else {
+                panic!("Expected the request to be rejected, got {response:?}");
+            };
+            assert_eq!(error.to_string(), "read-only mode");
+        }
+
+        mock.assert();
+        post.assert();
+        let statuses = middleware.statuses.lock().unwrap();
+        assert_eq!(*statuses, vec![(http::Method::GET, 200); CLIENTS]);
+    }
+}
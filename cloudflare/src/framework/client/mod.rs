@@ -1,15 +1,39 @@
+use crate::framework::endpoint::EndpointSpec;
+use crate::framework::response::{ApiResponse, Response};
+use std::future::Future;
 use std::net::IpAddr;
+use std::sync::Arc;
 use std::time::Duration;
 
 pub mod async_api;
 // There is no blocking support for wasm.
 #[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
 pub mod blocking_api;
+pub mod batch;
+pub mod codec;
+pub mod dry_run;
+pub mod middleware;
+pub mod options;
+pub mod rate_limit;
+pub mod retry;
+#[cfg(test)]
+mod test_support;
+mod trace;
+pub mod transport;
+
+use middleware::Middleware;
+use options::RequestOptions;
+use rate_limit::RateLimit;
+use retry::RetryPolicy;
 
 /// Configuration for the API client. Allows users to customize its behaviour.
+///
+/// `http_timeout`, `default_headers` and `resolve_ip` only apply to the `reqwest` client built by
+/// `new`. Clients built from a `reqwest` client or a transport only use the other settings.
 pub struct ClientConfig {
     /// The maximum time limit for an API request. If a request takes longer than this, it will be
     /// cancelled.
+    /// Requests can be given another limit, see [`RequestOptions::timeout`].
     /// Note: this configuration has no effect when the target is wasm32.
     pub http_timeout: Duration,
     /// A default set of HTTP headers which will be sent with each API request.
@@ -17,6 +41,13 @@ pub struct ClientConfig {
     /// A specific IP to use when establishing a connection
     /// Note: this configuration has no effect when the target is wasm32.
     pub resolve_ip: Option<IpAddr>,
+    /// How failed requests should be retried. Requests are not retried if this is `None`.
+    pub retry_policy: Option<RetryPolicy>,
+    /// Limits how fast requests are sent, across the client and all of its clones.
+    /// Requests are not limited if this is `None`.
+    pub rate_limit: Option<RateLimit>,
+    /// Hooks run around each request, in order. See [`Middleware`].
+    pub middleware: Vec<Arc<dyn Middleware>>,
 }
 
 impl Default for ClientConfig {
@@ -25,6 +56,73 @@ impl Default for ClientConfig {
             http_timeout: Duration::from_secs(30),
             default_headers: http::HeaderMap::default(),
             resolve_ip: None,
+            retry_policy: None,
+            rate_limit: None,
+            middleware: Vec::new(),
         }
     }
 }
+
+/// A Cloudflare API client which sends requests asynchronously, i.e. `async_api::Client`.
+///
+/// Code that is generic over this trait works with the async client and with test doubles. Blocking
+/// code uses [`BlockingApiClient`] instead. Both clients build requests and parse responses with
+/// the `codec` module, so they behave identically.
+pub trait ApiClient {
+    /// Issue an API request of the given type.
+    fn request<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+    ) -> impl Future<Output = ApiResponse<Endpoint::ResponseType>>
+    where
+        Endpoint: EndpointSpec + Send + Sync;
+
+    /// Issue an API request of the given type, with options which only apply to this request.
+    fn request_with<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+        options: &RequestOptions,
+    ) -> impl Future<Output = ApiResponse<Endpoint::ResponseType>>
+    where
+        Endpoint: EndpointSpec + Send + Sync;
+
+    /// Issue an API request of the given type, and return its result along with the status and
+    /// headers of the response.
+    fn request_with_meta<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+    ) -> impl Future<Output = ApiResponse<Response<Endpoint::ResponseType>>>
+    where
+        Endpoint: EndpointSpec + Send + Sync;
+}
+
+/// A Cloudflare API client which sends requests synchronously, i.e. `blocking_api::HttpApiClient`.
+///
+/// The blocking counterpart of [`ApiClient`]. Its methods block the calling thread, so they must
+/// not be called from async code.
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+pub trait BlockingApiClient {
+    /// Synchronously issue an API request of the given type.
+    fn request<Endpoint>(&self, endpoint: &Endpoint) -> ApiResponse<Endpoint::ResponseType>
+    where
+        Endpoint: EndpointSpec + Send + Sync;
+
+    /// Synchronously issue an API request of the given type, with options which only apply to
+    /// this request.
+    fn request_with<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+        options: &RequestOptions,
+    ) -> ApiResponse<Endpoint::ResponseType>
+    where
+        Endpoint: EndpointSpec + Send + Sync;
+
+    /// Synchronously issue an API request of the given type, and return its result along with the
+    /// status and headers of the response.
+    fn request_with_meta<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+    ) -> ApiResponse<Response<Endpoint::ResponseType>>
+    where
+        Endpoint: EndpointSpec + Send + Sync;
+}
@@ -0,0 +1,255 @@
+//! Settings of a single request, see [`RequestOptions`].
+use futures_util::future::{self, Either};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::{pin, Pin};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+/// Settings which apply to a single request, on top of those of the client. See
+/// `Client::request_with` and `HttpApiClient::request_with`.
+#[derive(Clone, Debug, Default)]
+pub struct RequestOptions {
+    /// The time limit for each attempt of the request, instead of the `http_timeout` of the
+    /// client. It is given to the transport as a [`RequestTimeout`] extension of the request.
+    pub timeout: Option<Duration>,
+    /// Headers added to the request, replacing the headers of the same names.
+    ///
+    /// Credential headers are ignored, as they are set from the credentials of the client.
+    pub headers: http::HeaderMap,
+    /// Aborts the request when cancelled.
+    ///
+    /// The async client drops the request as soon as the token is cancelled, even while waiting
+    /// for the API. The blocking client checks the token before each attempt.
+    pub cancellation: Option<CancellationToken>,
+}
+
+/// The time limit of a request, in the extensions of the requests given to transports.
+///
+/// The default transports apply it instead of the timeout of their `reqwest` client. Other
+/// transports should apply it too.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RequestTimeout(pub Duration);
+
+/// Cancels the requests it is given to, see [`RequestOptions::cancellation`].
+///
+/// Clones share the same state, so that the code which shuts a job down can cancel every request
+/// of the job. A cancelled token stays cancelled.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    state: Arc<CancellationState>,
+}
+
+#[derive(Default)]
+struct CancellationState {
+    cancelled: AtomicBool,
+    waiters: Mutex<Waiters>,
+}
+
+/// The tasks waiting for the token to be cancelled, by the key of their `Cancelled` future.
+#[derive(Default)]
+struct Waiters {
+    next_key: u64,
+    wakers: HashMap<u64, Waker>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Cancels the token, and wakes up the requests waiting on it.
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+        let wakers = std::mem::take(&mut self.waiters().wakers);
+        for waker in wakers.into_values() {
+            waker.wake();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Completes once the token is cancelled.
+    pub fn cancelled(&self) -> impl Future<Output = ()> + Send + '_ {
+        Cancelled {
+            token: self,
+            key: None,
+        }
+    }
+
+    /// Runs `future` until it completes, or returns `None` as soon as the token is cancelled, in
+    /// which case `future` is dropped.
+    pub async fn run_until_cancelled<F: Future>(&self, future: F) -> Option<F::Output> {
+        if self.is_cancelled() {
+            return None;
+        }
+        match future::select(pin!(future), pin!(self.cancelled())).await {
+            Either::Left((output, _)) => Some(output),
+            Either::Right(_) => None,
+        }
+    }
+
+    fn waiters(&self) -> MutexGuard<'_, Waiters> {
+        self.state
+            .waiters
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+struct Cancelled<'a> {
+    token: &'a CancellationToken,
+    /// The key of the waker registered by the last poll, if any.
+    key: Option<u64>,
+}
+
+impl Future for Cancelled<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let token = self.token;
+        let mut waiters = token.waiters();
+        // Checked while holding the lock, so that `cancel` takes the waker registered below.
+        if token.is_cancelled() {
+            return Poll::Ready(());
+        }
+        let key = match self.key {
+            Some(key) => key,
+            None => {
+                waiters.next_key += 1;
+                waiters.next_key
+            }
+        };
+        waiters.wakers.insert(key, cx.waker().clone());
+        drop(waiters);
+        self.key = Some(key);
+        Poll::Pending
+    }
+}
+
+impl Drop for Cancelled<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            self.token.waiters().wakers.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "reqwest")]
+    use crate::framework::client::test_support::*;
+    #[cfg(feature = "reqwest")]
+    use crate::framework::response::ApiFailure;
+    #[cfg(feature = "reqwest")]
+    use mockito::Server;
+
+    #[tokio::test]
+    async fn cancels_running_futures() {
+        let token = CancellationToken::new();
+        assert_eq!(token.run_until_cancelled(async { 1 }).await, Some(1));
+
+        let run = token.run_until_cancelled(future::pending::<()>());
+        let cancel = async {
+            futures_timer::Delay::new(Duration::from_millis(10)).await;
+            token.cancel();
+        };
+        let (result, ()) = future::join(run, cancel).await;
+        assert_eq!(result, None);
+        assert!(token.is_cancelled());
+        assert!(token.waiters().wakers.is_empty());
+
+        assert_eq!(token.run_until_cancelled(async { 1 }).await, None);
+    }
+
+    /// Test that both clients apply the headers, timeout and cancellation token of the options of
+    /// a request.
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn test_clients_apply_request_options() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("GET", "/dummy/raw")
+            .match_header("x-audit-reason", "cleanup")
+            .match_header("authorization", "Bearer dummy")
+            .with_status(200)
+            .with_header("content-type", "application/octet-stream")
+            .with_body("value")
+            .expect(CLIENTS)
+            .create();
+        let mut headers = http::HeaderMap::new();
+        headers.insert("x-audit-reason", "cleanup".parse().unwrap());
+        headers.insert("authorization", "Bearer other".parse().unwrap());
+        let options = RequestOptions {
+            headers,
+            ..RequestOptions::default()
+        };
+        let clients = TestClients::new(server.url());
+        for response in clients.request_with(&DummyRawEndpoint, &options) {
+            assert_eq!(response.unwrap(), b"value");
+        }
+        mock.assert();
+
+        // The server accepts connections, but never answers.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let options = RequestOptions {
+            timeout: Some(std::time::Duration::from_millis(50)),
+            ..RequestOptions::default()
+        };
+        for response in TestClients::new(url).request_with(&DummyRawEndpoint, &options) {
+            assert!(matches!(response, Err(ApiFailure::Transport(error)) if error.is_timeout()));
+        }
+
+        let mock = server.mock("GET", "/dummy/raw").expect(0).create();
+        let token = CancellationToken::new();
+        token.cancel();
+        let options = RequestOptions {
+            cancellation: Some(token),
+            ..RequestOptions::default()
+        };
+        for response in clients.request_with(&DummyRawEndpoint, &options) {
+            assert_eq!(response, Err(ApiFailure::Cancelled));
+        }
+        mock.assert();
+    }
+
+    /// Test that the async client drops a request which is waiting for the API when it is
+    /// cancelled.
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn test_request_is_cancelled_while_in_flight() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let clients = TestClients::new(format!("http://{}", listener.local_addr().unwrap()));
+        let token = CancellationToken::new();
+        let options = RequestOptions {
+            cancellation: Some(token.clone()),
+            ..RequestOptions::default()
+        };
+
+        let request = clients.client.request_with(&DummyRawEndpoint, &options);
+        let cancel = async {
+            futures_timer::Delay::new(std::time::Duration::from_millis(20)).await;
+            token.cancel();
+        };
+        let (response, ()) = clients
+            .runtime
+            .block_on(futures_util::future::join(request, cancel));
+
+        assert_eq!(response, Err(ApiFailure::Cancelled));
+    }
+}
@@ -0,0 +1,240 @@
+use http::HeaderMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+// `std::time::Instant` panics on wasm32.
+use web_time::Instant;
+
+/// Configuration of the client-side rate limiter.
+///
+/// The limiter is a token bucket: every request takes one token out of the bucket, and tokens are
+/// added back at a steady rate. Once the bucket is empty, requests wait until a token is available.
+/// Clones of a client share the same bucket.
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    /// The maximum number of tokens in the bucket, i.e. the largest burst of requests allowed.
+    pub bucket_size: u32,
+    /// The number of tokens added back to the bucket every `refill_period`.
+    pub refill_amount: u32,
+    /// See `refill_amount`. Periods shorter than `MIN_REFILL_PERIOD` are raised to it.
+    pub refill_period: Duration,
+}
+
+impl Default for RateLimit {
+    /// Cloudflare's global API rate limit: 1200 requests per 5 minutes.
+    fn default() -> Self {
+        RateLimit {
+            bucket_size: 1200,
+            refill_amount: 1200,
+            refill_period: Duration::from_secs(300),
+        }
+    }
+}
+
+/// The shortest `RateLimit::refill_period`. A zero period would refill the bucket infinitely fast.
+pub const MIN_REFILL_PERIOD: Duration = Duration::from_millis(1);
+
+/// A token bucket shared by a client and all of its clones.
+#[derive(Clone, Debug)]
+pub(crate) struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    /// Tokens added per second.
+    rate: f64,
+    /// Can be negative, when requests are already waiting for tokens.
+    tokens: f64,
+    /// Tokens are refilled from this instant onwards. May be in the future when Cloudflare told
+    /// us the quota is exhausted until then.
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(config: &RateLimit) -> Self {
+        let capacity = f64::from(config.bucket_size.max(1));
+        let refill_period = config.refill_period.max(MIN_REFILL_PERIOD);
+        let rate = f64::from(config.refill_amount.max(1)) / refill_period.as_secs_f64();
+        RateLimiter {
+            bucket: Arc::new(Mutex::new(Bucket {
+                capacity,
+                rate,
+                tokens: capacity,
+                last_refill: Instant::now(),
+            })),
+        }
+    }
+
+    /// Takes a token out of the bucket, returning how long the caller has to wait before it may
+    /// send its request.
+    pub(crate) fn reserve(&self) -> Duration {
+        self.reserve_at(Instant::now())
+    }
+
+    /// Resynchronizes the bucket with the quota Cloudflare reports in response headers, if any.
+    pub(crate) fn update_from_headers(&self, headers: &HeaderMap) {
+        if let Some(quota) = Quota::from_headers(headers) {
+            self.update_at(quota, Instant::now());
+        }
+    }
+
+    fn reserve_at(&self, now: Instant) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        bucket.refill(now);
+        bucket.tokens -= 1.0;
+        let wait = bucket.last_refill.saturating_duration_since(now);
+        if bucket.tokens >= 0.0 {
+            wait
+        } else {
+            wait + Duration::from_secs_f64(-bucket.tokens / bucket.rate)
+        }
+    }
+
+    fn update_at(&self, quota: Quota, now: Instant) {
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        bucket.refill(now);
+        bucket.tokens = bucket.tokens.min(f64::from(quota.remaining));
+        if quota.remaining == 0 {
+            if let Some(reset) = quota.reset {
+                bucket.last_refill = bucket.last_refill.max(now + reset);
+            }
+        }
+    }
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        if now <= self.last_refill {
+            return;
+        }
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = now;
+    }
+}
+
+/// The remaining quota, as reported by Cloudflare.
+#[derive(Debug, PartialEq, Eq)]
+struct Quota {
+    remaining: u32,
+    reset: Option<Duration>,
+}
+
+impl Quota {
+    /// Parses either the `Ratelimit` header (e.g. `"default";r=50;t=30`), or the legacy
+    /// `X-RateLimit-Remaining` and `X-RateLimit-Reset` headers.
+    fn from_headers(headers: &HeaderMap) -> Option<Quota> {
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+        if let Some(value) = header("ratelimit") {
+            let mut remaining = None;
+            let mut reset = None;
+            for param in value.split(';') {
+                match param.trim().split_once('=') {
+                    Some(("r", r)) => remaining = r.trim().parse().ok(),
+                    Some(("t", t)) => reset = t.trim().parse().ok().map(Duration::from_secs),
+                    _ => {}
+                }
+            }
+            return remaining.map(|remaining| Quota { remaining, reset });
+        }
+
+        let remaining = header("x-ratelimit-remaining")?.trim().parse().ok()?;
+        let reset = header("x-ratelimit-reset")
+            .and_then(|t| t.trim().parse().ok())
+            .map(Duration::from_secs);
+        Some(Quota { remaining, reset })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    fn limiter(bucket_size: u32, per_second: u32) -> RateLimiter {
+        RateLimiter::new(&RateLimit {
+            bucket_size,
+            refill_amount: per_second,
+            refill_period: Duration::from_secs(1),
+        })
+    }
+
+    #[test]
+    fn bursts_up_to_bucket_size_then_waits() {
+        let limiter = limiter(2, 4);
+        let now = Instant::now();
+        assert_eq!(limiter.reserve_at(now), Duration::ZERO);
+        assert_eq!(limiter.reserve_at(now), Duration::ZERO);
+        assert_eq!(limiter.reserve_at(now), Duration::from_millis(250));
+        assert_eq!(limiter.reserve_at(now), Duration::from_millis(500));
+        // Half a second later, the two refilled tokens are already promised to waiting requests.
+        assert_eq!(
+            limiter.reserve_at(now + Duration::from_millis(500)),
+            Duration::from_millis(250)
+        );
+    }
+
+    #[test]
+    fn clones_share_the_bucket() {
+        let limiter = limiter(1, 1);
+        let clone = limiter.clone();
+        let now = Instant::now();
+        assert_eq!(limiter.reserve_at(now), Duration::ZERO);
+        assert_eq!(clone.reserve_at(now), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn raises_zero_refill_period() {
+        let limiter = RateLimiter::new(&RateLimit {
+            bucket_size: 1,
+            refill_amount: 1,
+            refill_period: Duration::ZERO,
+        });
+        let now = Instant::now();
+        assert_eq!(limiter.reserve_at(now), Duration::ZERO);
+        assert_eq!(limiter.reserve_at(now), MIN_REFILL_PERIOD);
+        assert_eq!(limiter.reserve_at(now), 2 * MIN_REFILL_PERIOD);
+    }
+
+    #[test]
+    fn resyncs_from_headers() {
+        let limiter = limiter(100, 10);
+        let now = Instant::now();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "ratelimit",
+            HeaderValue::from_static("\"default\";r=0;t=30"),
+        );
+        let quota = Quota::from_headers(&headers).unwrap();
+        assert_eq!(
+            quota,
+            Quota {
+                remaining: 0,
+                reset: Some(Duration::from_secs(30))
+            }
+        );
+
+        limiter.update_at(quota, now);
+        assert_eq!(
+            limiter.reserve_at(now),
+            Duration::from_secs(30) + Duration::from_millis(100)
+        );
+    }
+
+    #[test]
+    fn parses_legacy_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("12"));
+        assert_eq!(
+            Quota::from_headers(&headers),
+            Some(Quota {
+                remaining: 12,
+                reset: None
+            })
+        );
+        assert_eq!(Quota::from_headers(&HeaderMap::new()), None);
+    }
+}
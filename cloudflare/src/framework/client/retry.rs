@@ -0,0 +1,560 @@
+use crate::framework::auth::CredentialsProvider;
+use crate::framework::client::codec;
+use crate::framework::client::middleware::{self, Middleware, ReceivedBody};
+use crate::framework::client::rate_limit::RateLimiter;
+use crate::framework::client::transport::TransportError;
+use crate::framework::response::{ApiFailure, ApiResponse};
+use bytes::Bytes;
+use http::header::RETRY_AFTER;
+use http::{HeaderMap, Method, StatusCode};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Controls how failed API requests are retried.
+///
+/// Requests are retried when Cloudflare answers with `429 Too Many Requests` or a `5xx` status,
+/// and when the connection to the API could not be established or timed out.
+/// Between attempts, the client waits for an exponentially growing, randomly jittered delay.
+/// If a `429` response carries a `Retry-After` header, that delay is used instead.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts per request, including the first one.
+    /// A value of `1` (or `0`) disables retries.
+    pub max_attempts: u32,
+    /// The upper bound of the delay before the first retry. It doubles with every attempt.
+    pub initial_backoff: Duration,
+    /// The maximum delay between two attempts, unless Cloudflare asks for more via `Retry-After`.
+    pub max_backoff: Duration,
+    /// Whether non-idempotent requests (e.g. POST or PATCH) should be retried as well.
+    /// Only GET, HEAD, OPTIONS, PUT and DELETE requests are retried by default, since retrying
+    /// anything else could apply the same change twice.
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+/// What happened during a single attempt, as far as the retry policy is concerned.
+pub(crate) enum Attempt<'a> {
+    /// The API answered with the given status and headers.
+    Response(StatusCode, &'a HeaderMap),
+    /// The request could not be sent.
+    Error(&'a TransportError),
+}
+
+impl RetryPolicy {
+    /// Returns how long to wait before retrying a request that just made its `attempt`-th try
+    /// (starting at 1), or `None` if it should not be retried.
+    pub(crate) fn backoff(
+        &self,
+        method: &Method,
+        attempt: u32,
+        outcome: Attempt<'_>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts || !self.is_retryable_method(method) {
+            return None;
+        }
+        match outcome {
+            Attempt::Response(status, headers) => {
+                if status == StatusCode::TOO_MANY_REQUESTS {
+                    if let Some(delay) = retry_after(headers) {
+                        return Some(delay);
+                    }
+                } else if !status.is_server_error() {
+                    return None;
+                }
+            }
+            Attempt::Error(error) => {
+                if !error.is_connect() && !error.is_timeout() {
+                    return None;
+                }
+            }
+        }
+        Some(self.jittered_backoff(attempt))
+    }
+
+    fn is_retryable_method(&self, method: &Method) -> bool {
+        self.retry_non_idempotent
+            || matches!(
+                *method,
+                Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE
+            )
+    }
+
+    /// "Full jitter": a random delay between zero and the exponential backoff for this attempt.
+    fn jittered_backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let ceiling = self
+            .initial_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff);
+        ceiling.mul_f64(fastrand::f64())
+    }
+}
+
+/// The attempts at sending a request: the middleware and rate limiter run before each attempt,
+/// failed attempts are retried according to the retry policy, and rejected credentials are
+/// refreshed once.
+///
+/// It only decides what to do next, see `Step`: the clients do the waiting and the sending, so
+/// that both behave identically.
+pub(crate) struct Attempts<'a> {
+    /// The request as built, before the middleware ran.
+    request: http::Request<Bytes>,
+    retry_policy: Option<&'a RetryPolicy>,
+    rate_limiter: Option<&'a RateLimiter>,
+    middleware: &'a [Arc<dyn Middleware>],
+    /// `None` once the credentials were refreshed.
+    credentials: Option<&'a dyn CredentialsProvider>,
+    attempt: u32,
+    /// The request of the current attempt, while it waits for the rate limiter.
+    ready: Option<http::Request<Bytes>>,
+    /// The request of the current attempt as it was sent, for the middleware.
+    sent: Option<http::Request<Bytes>>,
+}
+
+/// What a client does next with the request of `Attempts`.
+pub(crate) enum Step<B> {
+    /// Wait for this long, then call `Attempts::resume`.
+    Wait(Duration),
+    /// Send this request, then call `Attempts::received` with what the transport returned.
+    Send(http::Request<Bytes>),
+    /// Return this result.
+    Done(ApiResponse<http::Response<B>>),
+}
+
+impl<'a> Attempts<'a> {
+    pub(crate) fn new(
+        request: http::Request<Bytes>,
+        retry_policy: Option<&'a RetryPolicy>,
+        rate_limiter: Option<&'a RateLimiter>,
+        middleware: &'a [Arc<dyn Middleware>],
+        credentials: &'a dyn CredentialsProvider,
+    ) -> Self {
+        Attempts {
+            request,
+            retry_policy,
+            rate_limiter,
+            middleware,
+            credentials: Some(credentials),
+            attempt: 1,
+            ready: None,
+            sent: None,
+        }
+    }
+
+    /// Sends the request a single time, e.g. because its body can only be read once.
+    pub(crate) fn once(mut self) -> Self {
+        self.retry_policy = None;
+        self.credentials = None;
+        self
+    }
+
+    /// The first step.
+    pub(crate) fn start<B>(&mut self) -> Step<B> {
+        self.resume()
+    }
+
+    /// The step after a `Step::Wait`.
+    pub(crate) fn resume<B>(&mut self) -> Step<B> {
+        let request = match self.ready.take() {
+            Some(request) => request,
+            None => {
+                let mut request = self.request.clone();
+                if let Err(error) = middleware::before_send(self.middleware, &mut request) {
+                    return Step::Done(Err(error));
+                }
+                let delay = self
+                    .rate_limiter
+                    .map_or(Duration::ZERO, RateLimiter::reserve);
+                if !delay.is_zero() {
+                    self.ready = Some(request);
+                    return Step::Wait(delay);
+                }
+                request
+            }
+        };
+        // The request is consumed by the transport, but the middleware sees it with the response.
+        self.sent = (!self.middleware.is_empty()).then(|| request.clone());
+        Step::Send(request)
+    }
+
+    /// The step after a `Step::Send`, given what the transport returned.
+    pub(crate) fn received<B: ReceivedBody>(
+        &mut self,
+        result: Result<http::Response<B>, TransportError>,
+    ) -> Step<B> {
+        if let Ok(response) = &result {
+            if let Some(limiter) = self.rate_limiter {
+                limiter.update_from_headers(response.headers());
+            }
+            if let Some(request) = self.sent.take() {
+                middleware::after_receive(self.middleware, &request, response);
+            }
+        }
+
+        let outcome = match &result {
+            Ok(response) => Attempt::Response(response.status(), response.headers()),
+            Err(error) => Attempt::Error(error),
+        };
+        let backoff = self
+            .retry_policy
+            .and_then(|policy| policy.backoff(self.request.method(), self.attempt, outcome));
+        if let Some(delay) = backoff {
+            self.attempt += 1;
+            return Step::Wait(delay);
+        }
+
+        match result {
+            Ok(response)
+                if matches!(
+                    response.status(),
+                    StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+                ) =>
+            {
+                let retry = self
+                    .credentials
+                    .take()
+                    .and_then(|credentials| codec::reauthenticate(&self.request, credentials));
+                match retry {
+                    Some(request) => {
+                        self.request = request;
+                        self.attempt = 1;
+                        self.resume()
+                    }
+                    None => Step::Done(Ok(response)),
+                }
+            }
+            result => Step::Done(result.map_err(ApiFailure::from)),
+        }
+    }
+}
+
+/// Parses a `Retry-After` header, given either in seconds or as an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framework::auth::{Credentials, CredentialsError};
+    use crate::framework::client::async_api::Client;
+    use crate::framework::client::test_support::*;
+    #[cfg(feature = "reqwest")]
+    use crate::framework::client::ClientConfig;
+    use crate::framework::Environment;
+    use http::HeaderValue;
+    #[cfg(feature = "reqwest")]
+    use mockito::Server;
+    #[cfg(feature = "reqwest")]
+    use serde_json::json;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn retries_server_errors_until_max_attempts() {
+        let policy = RetryPolicy::default();
+        let headers = HeaderMap::new();
+        let outcome = || Attempt::Response(StatusCode::SERVICE_UNAVAILABLE, &headers);
+
+        let delay = policy.backoff(&Method::GET, 1, outcome()).unwrap();
+        assert!(delay <= policy.initial_backoff);
+        let delay = policy.backoff(&Method::GET, 2, outcome()).unwrap();
+        assert!(delay <= policy.initial_backoff * 2);
+        assert_eq!(policy.backoff(&Method::GET, 3, outcome()), None);
+    }
+
+    #[test]
+    fn does_not_retry_client_errors() {
+        let policy = RetryPolicy::default();
+        let headers = HeaderMap::new();
+        for status in [StatusCode::BAD_REQUEST, StatusCode::NOT_FOUND] {
+            let outcome = Attempt::Response(status, &headers);
+            assert_eq!(policy.backoff(&Method::GET, 1, outcome), None);
+        }
+    }
+
+    #[test]
+    fn retries_post_only_when_opted_in() {
+        let headers = HeaderMap::new();
+        let outcome = || Attempt::Response(StatusCode::BAD_GATEWAY, &headers);
+
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(&Method::POST, 1, outcome()), None);
+        assert_eq!(policy.backoff(&Method::PATCH, 1, outcome()), None);
+
+        let policy = RetryPolicy {
+            retry_non_idempotent: true,
+            ..RetryPolicy::default()
+        };
+        assert!(policy.backoff(&Method::POST, 1, outcome()).is_some());
+    }
+
+    #[test]
+    fn honors_retry_after() {
+        let policy = RetryPolicy::default();
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("42"));
+        let outcome = Attempt::Response(StatusCode::TOO_MANY_REQUESTS, &headers);
+        assert_eq!(
+            policy.backoff(&Method::GET, 1, outcome),
+            Some(Duration::from_secs(42))
+        );
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        let outcome = Attempt::Response(StatusCode::TOO_MANY_REQUESTS, &headers);
+        assert_eq!(
+            policy.backoff(&Method::GET, 1, outcome),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 100,
+            max_backoff: Duration::from_secs(1),
+            ..RetryPolicy::default()
+        };
+        let headers = HeaderMap::new();
+        let outcome = Attempt::Response(StatusCode::INTERNAL_SERVER_ERROR, &headers);
+        let delay = policy.backoff(&Method::GET, 50, outcome).unwrap();
+        assert!(delay <= Duration::from_secs(1));
+    }
+
+    fn response(status: StatusCode) -> Result<http::Response<Bytes>, TransportError> {
+        let mut response = http::Response::new(Bytes::new());
+        *response.status_mut() = status;
+        Ok(response)
+    }
+
+    #[test]
+    fn attempts_retry_until_success() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::ZERO,
+            ..RetryPolicy::default()
+        };
+        let credentials = Credentials::Service {
+            key: "key".to_owned().into(),
+        };
+        let mut attempts = Attempts::new(
+            http::Request::new(Bytes::new()),
+            Some(&policy),
+            None,
+            &[],
+            &credentials,
+        );
+
+        assert!(matches!(attempts.start::<Bytes>(), Step::Send(_)));
+        let step = attempts.received(response(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(matches!(step, Step::Wait(_)));
+        assert!(matches!(attempts.resume::<Bytes>(), Step::Send(_)));
+        match attempts.received(response(StatusCode::OK)) {
+            Step::Done(Ok(response)) => assert_eq!(response.status(), StatusCode::OK),
+            _ => panic!("expected the response"),
+        }
+    }
+
+    #[test]
+    fn attempts_reauthenticate_once() {
+        /// Returns a different token every time.
+        struct Rotating(AtomicU32);
+
+        impl CredentialsProvider for Rotating {
+            fn credentials(&self) -> Result<Credentials, CredentialsError> {
+                let count = self.0.fetch_add(1, Ordering::SeqCst);
+                Ok(Credentials::UserAuthToken {
+                    token: format!("token-{count}").into(),
+                })
+            }
+        }
+
+        let credentials = Rotating(AtomicU32::new(0));
+        let mut attempts = Attempts::new(
+            http::Request::new(Bytes::new()),
+            None,
+            None,
+            &[],
+            &credentials,
+        );
+
+        assert!(matches!(attempts.start::<Bytes>(), Step::Send(_)));
+        match attempts.received(response(StatusCode::UNAUTHORIZED)) {
+            Step::Send(request) => {
+                assert_eq!(request.headers()["Authorization"], "Bearer token-0");
+            }
+            _ => panic!("expected a retry with new credentials"),
+        }
+        match attempts.received(response(StatusCode::UNAUTHORIZED)) {
+            Step::Done(Ok(response)) => {
+                assert_eq!(response.status(), StatusCode::UNAUTHORIZED)
+            }
+            _ => panic!("expected the response"),
+        }
+    }
+
+    /// Test that both clients retry server errors until the retry policy gives up.
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn test_server_error_is_retried() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("GET", "/dummy/json")
+            .with_status(503)
+            .with_header("content-type", "application/json")
+            .with_body(json!({"errors": []}).to_string())
+            .expect(3 * CLIENTS)
+            .create();
+
+        let clients = TestClients::with_config(server.url(), fast_retry_config);
+        let responses = clients.request(&DummyJsonEndpoint);
+
+        mock.assert();
+        for result in responses {
+            assert!(matches!(result, Err(ApiFailure::Error(status, ..)) if status.as_u16() == 503));
+        }
+    }
+
+    /// Test that both clients retry rate-limited requests after the delay given by `Retry-After`.
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn test_rate_limited_request_is_retried() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("GET", "/dummy/json")
+            .with_status(429)
+            .with_header("retry-after", "0")
+            .expect(3 * CLIENTS)
+            .create();
+
+        let clients = TestClients::with_config(server.url(), fast_retry_config);
+        let _ = clients.request(&DummyJsonEndpoint);
+
+        mock.assert();
+    }
+
+    /// Test that neither client retries non-idempotent requests by default.
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn test_post_is_not_retried_by_default() {
+        let mut server = Server::new();
+        let mock = server
+            .mock("POST", "/dummy/json")
+            .with_status(500)
+            .expect(CLIENTS)
+            .create();
+
+        let clients = TestClients::with_config(server.url(), fast_retry_config);
+        let _ = clients.request(&DummyJsonRequestEndpoint);
+
+        mock.assert();
+    }
+
+    /// Test that both clients retry once with refreshed credentials when the API rejects them, and
+    /// not when the credentials are unchanged.
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn test_clients_refresh_rejected_credentials() {
+        struct RefreshingProvider(AtomicU32);
+
+        impl CredentialsProvider for RefreshingProvider {
+            fn credentials(&self) -> Result<Credentials, CredentialsError> {
+                Ok(Credentials::UserAuthToken {
+                    token: format!("token-{}", self.0.load(Ordering::Relaxed)).into(),
+                })
+            }
+
+            fn refresh(&self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let mut server = Server::new();
+        let rejected = server
+            .mock("GET", "/dummy/nothing")
+            .match_header("authorization", "Bearer token-0")
+            .with_status(401)
+            .with_body(r#"{"errors": [{"code": 10000, "message": "Authentication error"}]}"#)
+            .expect(CLIENTS)
+            .create();
+        let accepted = server
+            .mock("GET", "/dummy/nothing")
+            .match_header("authorization", "Bearer token-1")
+            .with_status(200)
+            .with_body(r#"{"result": null, "success": true}"#)
+            .expect(CLIENTS)
+            .create();
+        let unchanged = server
+            .mock("GET", "/dummy/nothing")
+            .match_header("authorization", "Bearer dummy")
+            .with_status(403)
+            .with_body(r#"{"errors": [{"code": 10000, "message": "Authentication error"}]}"#)
+            .expect(CLIENTS)
+            .create();
+
+        let providers: [fn() -> Arc<dyn CredentialsProvider>; 2] = [
+            || Arc::new(RefreshingProvider(AtomicU32::new(0))),
+            || {
+                Arc::new(Credentials::UserAuthToken {
+                    token: "dummy".into(),
+                })
+            },
+        ];
+        let mut statuses = vec![];
+        for provider in providers {
+            let clients =
+                TestClients::with_credentials(server.url(), provider, ClientConfig::default);
+            let responses = clients.request(&DummyNothingEndpoint);
+            statuses.extend(
+                responses
+                    .into_iter()
+                    .map(|response| response.err().and_then(|failure| failure.status())),
+            );
+        }
+
+        rejected.assert();
+        accepted.assert();
+        unchanged.assert();
+        let (refreshed, unchanged) = statuses.split_at(CLIENTS);
+        assert!(refreshed.iter().all(Option::is_none));
+        assert!(unchanged
+            .iter()
+            .all(|status| *status == Some(http::StatusCode::FORBIDDEN)));
+    }
+
+    /// Test that connection errors raised by a transport are retried.
+    #[tokio::test]
+    async fn test_transport_error_is_retried() {
+        let transport = FakeTransport::default();
+        let client = Client::new_with_transport(
+            transport.clone(),
+            Credentials::UserAuthToken {
+                token: "dummy".into(),
+            },
+            fast_retry_config(),
+            Environment::Custom("https://example.com/".into()),
+        );
+
+        let result = client.request(&DummyJsonEndpoint).await;
+
+        assert!(matches!(result, Err(ApiFailure::Transport(error)) if error.is_connect()));
+        assert_eq!(transport.requests.lock().unwrap().len(), 3);
+    }
+}
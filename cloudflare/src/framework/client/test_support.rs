@@ -0,0 +1,464 @@
+//! Endpoints, transports and clients shared by the tests of the clients.
+// Most endpoints are only sent to a mock server, with `reqwest`.
+#![cfg_attr(not(feature = "reqwest"), allow(dead_code))]
+#[cfg(feature = "reqwest")]
+use crate::framework::auth::{Credentials, CredentialsProvider};
+#[cfg(feature = "reqwest")]
+use crate::framework::client::async_api::Client;
+use crate::framework::client::retry::RetryPolicy;
+use crate::framework::client::transport::{
+    Transport, TransportError, TransportErrorKind, TransportFuture,
+};
+use crate::framework::client::ClientConfig;
+#[cfg(feature = "blocking")]
+use crate::framework::client::{blocking_api::HttpApiClient, BlockingApiClient};
+#[cfg(feature = "reqwest")]
+use crate::framework::client::{options::RequestOptions, ApiClient};
+use crate::framework::endpoint::{serialize_query, EndpointSpec, Paginated};
+use crate::framework::endpoint::{FilePart, MultipartPart, RequestBody, StreamBody};
+#[cfg(feature = "reqwest")]
+use crate::framework::response::{ApiResponse, Response};
+use crate::framework::response::{ApiResult, ApiSuccess, ResultInfo};
+#[cfg(feature = "reqwest")]
+use crate::framework::Environment;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+//region Endpoint that returns JSON (ApiSuccess).
+#[derive(Debug)]
+pub(crate) struct DummyJsonEndpoint;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct DummyJsonResponse {
+    pub(crate) message: String,
+}
+
+impl ApiResult for DummyJsonResponse {}
+impl ApiResult for Vec<DummyJsonResponse> {}
+
+impl EndpointSpec for DummyJsonEndpoint {
+    type JsonResponse = DummyJsonResponse;
+    type ResponseType = ApiSuccess<Self::JsonResponse>;
+
+    fn method(&self) -> http::Method {
+        http::Method::GET
+    }
+
+    fn path(&self) -> String {
+        "/dummy/json".into()
+    }
+}
+//endregion
+
+//region Endpoint that returns raw bytes.
+#[derive(Debug)]
+pub(crate) struct DummyRawEndpoint;
+
+impl EndpointSpec for DummyRawEndpoint {
+    type JsonResponse = ();
+    type ResponseType = Vec<u8>;
+
+    fn method(&self) -> http::Method {
+        http::Method::GET
+    }
+
+    fn path(&self) -> String {
+        "/dummy/raw".into()
+    }
+}
+//endregion
+
+//region Endpoint that returns nothing.
+#[derive(Debug)]
+pub(crate) struct DummyNothingEndpoint;
+
+impl EndpointSpec for DummyNothingEndpoint {
+    type JsonResponse = ();
+    type ResponseType = ApiSuccess<Self::JsonResponse>;
+
+    fn method(&self) -> http::Method {
+        http::Method::GET
+    }
+
+    fn path(&self) -> String {
+        "/dummy/nothing".into()
+    }
+}
+//endregion
+
+//region Endpoint that returns an empty body.
+#[derive(Debug)]
+pub(crate) struct DummyEmptyEndpoint;
+
+impl EndpointSpec for DummyEmptyEndpoint {
+    type JsonResponse = ();
+    type ResponseType = ();
+
+    fn method(&self) -> http::Method {
+        http::Method::DELETE
+    }
+
+    fn path(&self) -> String {
+        "/dummy/empty".into()
+    }
+}
+//endregion
+
+//region Endpoint that sends a JSON request.
+#[derive(Debug)]
+pub(crate) struct DummyJsonRequestEndpoint;
+
+impl EndpointSpec for DummyJsonRequestEndpoint {
+    type JsonResponse = ();
+    type ResponseType = ApiSuccess<Self::JsonResponse>;
+
+    fn method(&self) -> http::Method {
+        http::Method::POST
+    }
+
+    fn path(&self) -> String {
+        "/dummy/json".into()
+    }
+
+    fn body(&self) -> Option<RequestBody> {
+        Some(RequestBody::Json(json!({"key": "value"}).to_string()))
+    }
+}
+//endregion
+
+//region Endpoint whose body cannot be serialized to JSON.
+#[derive(Debug)]
+pub(crate) struct DummyUnserializableEndpoint {
+    // The keys of JSON objects must be strings.
+    pub(crate) body: std::collections::BTreeMap<(u8, u8), u8>,
+}
+
+impl EndpointSpec for DummyUnserializableEndpoint {
+    type JsonResponse = ();
+    type ResponseType = ApiSuccess<Self::JsonResponse>;
+
+    fn method(&self) -> http::Method {
+        http::Method::PUT
+    }
+
+    fn path(&self) -> String {
+        "/dummy/json".into()
+    }
+
+    fn body(&self) -> Option<RequestBody> {
+        Some(RequestBody::Serialize(&self.body))
+    }
+}
+//endregion
+
+//region Endpoint that sends raw bytes.
+#[derive(Debug)]
+pub(crate) struct DummyRawRequestEndpoint;
+
+impl EndpointSpec for DummyRawRequestEndpoint {
+    type JsonResponse = ();
+    type ResponseType = Vec<u8>;
+
+    fn method(&self) -> http::Method {
+        http::Method::POST
+    }
+
+    fn path(&self) -> String {
+        "/dummy/raw".into()
+    }
+
+    fn body(&self) -> Option<RequestBody> {
+        Some(RequestBody::Raw(b"raw content".to_vec()))
+    }
+}
+//endregion
+
+//region Endpoint that sends a multipart request.
+#[derive(Debug)]
+pub(crate) struct DummyMultipartEndpoint;
+
+impl EndpointSpec for DummyMultipartEndpoint {
+    type JsonResponse = ();
+    type ResponseType = ApiSuccess<Self::JsonResponse>;
+
+    fn method(&self) -> http::Method {
+        http::Method::POST
+    }
+
+    fn path(&self) -> String {
+        "/dummy/multipart".into()
+    }
+
+    fn body(&self) -> Option<RequestBody> {
+        Some(RequestBody::MultiPart(&DummyMultipart))
+    }
+}
+
+pub(crate) struct DummyMultipart;
+
+impl crate::framework::endpoint::MultipartBody for DummyMultipart {
+    fn parts(&self) -> Vec<(String, MultipartPart)> {
+        vec![("key".into(), MultipartPart::Text("value".into()))]
+    }
+}
+//endregion
+
+//region Endpoint that sends a streamed body.
+#[derive(Debug)]
+pub(crate) struct DummyStreamEndpoint {
+    pub(crate) body: StreamBody,
+}
+
+impl EndpointSpec for DummyStreamEndpoint {
+    type JsonResponse = ();
+    type ResponseType = ApiSuccess<Self::JsonResponse>;
+
+    fn method(&self) -> http::Method {
+        http::Method::PUT
+    }
+
+    fn path(&self) -> String {
+        "/dummy/stream".into()
+    }
+
+    fn body(&self) -> Option<RequestBody> {
+        Some(RequestBody::Stream(&self.body))
+    }
+}
+//endregion
+
+//region Endpoint that sends a file in a multipart request.
+#[derive(Debug)]
+pub(crate) struct DummyFileEndpoint {
+    pub(crate) path: std::path::PathBuf,
+}
+
+impl EndpointSpec for DummyFileEndpoint {
+    type JsonResponse = ();
+    type ResponseType = ApiSuccess<Self::JsonResponse>;
+
+    fn method(&self) -> http::Method {
+        http::Method::PUT
+    }
+
+    fn path(&self) -> String {
+        "/dummy/file".into()
+    }
+
+    fn body(&self) -> Option<RequestBody> {
+        Some(RequestBody::MultiPart(self))
+    }
+}
+
+impl crate::framework::endpoint::MultipartBody for DummyFileEndpoint {
+    fn parts(&self) -> Vec<(String, MultipartPart)> {
+        let file = FilePart::new(&self.path).content_type("application/javascript+module");
+        vec![
+            ("metadata".into(), MultipartPart::Text("{}".into())),
+            ("worker.js".into(), MultipartPart::File(file)),
+        ]
+    }
+}
+//endregion
+
+//region Endpoint that sends a request with query parameters.
+#[derive(Debug)]
+pub(crate) struct DummyJsonRequestWithQueryEndpoint;
+
+#[derive(Debug, Serialize)]
+struct DummyJsonRequestWithQueryParams {
+    key: String,
+}
+
+impl EndpointSpec for DummyJsonRequestWithQueryEndpoint {
+    type JsonResponse = ();
+    type ResponseType = ApiSuccess<Self::JsonResponse>;
+
+    fn method(&self) -> http::Method {
+        http::Method::POST
+    }
+
+    fn path(&self) -> String {
+        "/dummy/json".into()
+    }
+
+    fn query(&self) -> Option<String> {
+        serialize_query(&DummyJsonRequestWithQueryParams {
+            key: "value".into(),
+        })
+    }
+}
+//endregion
+
+//region Endpoint that lists items across pages.
+#[derive(Debug)]
+pub(crate) struct DummyListEndpoint {
+    pub(crate) page: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+struct DummyListParams {
+    page: Option<u32>,
+}
+
+impl EndpointSpec for DummyListEndpoint {
+    type JsonResponse = Vec<DummyJsonResponse>;
+    type ResponseType = ApiSuccess<Self::JsonResponse>;
+
+    fn method(&self) -> http::Method {
+        http::Method::GET
+    }
+
+    fn path(&self) -> String {
+        "/dummy/list".into()
+    }
+
+    fn query(&self) -> Option<String> {
+        serialize_query(&DummyListParams { page: self.page })
+    }
+}
+
+impl Paginated for DummyListEndpoint {
+    fn next_page(&mut self, result_info: &ResultInfo) -> bool {
+        self.page = result_info.next_page();
+        self.page.is_some()
+    }
+}
+//endregion
+
+/// A transport which records requests, and answers them with the given status and body,
+/// or fails to connect if there is none.
+#[derive(Clone, Default)]
+pub(crate) struct FakeTransport {
+    pub(crate) response: Option<(u16, &'static str)>,
+    pub(crate) requests: Arc<Mutex<Vec<http::Request<Bytes>>>>,
+}
+
+impl Transport for FakeTransport {
+    fn send(&self, request: http::Request<Bytes>) -> TransportFuture<'_> {
+        self.requests.lock().unwrap().push(request);
+        Box::pin(async move {
+            let (status, body) = self.response.ok_or_else(|| {
+                TransportError::new(TransportErrorKind::Connect, "connection refused")
+            })?;
+            Ok(http::Response::builder()
+                .status(status)
+                .body(Bytes::from_static(body.as_bytes()))
+                .unwrap())
+        })
+    }
+}
+
+/// The number of clients in `TestClients`.
+#[cfg(feature = "reqwest")]
+pub(crate) const CLIENTS: usize = if cfg!(feature = "blocking") { 2 } else { 1 };
+
+/// The async client and, if enabled, the blocking client, built alike, with a runtime to run
+/// the requests of the async client.
+#[cfg(feature = "reqwest")]
+pub(crate) struct TestClients {
+    pub(crate) runtime: tokio::runtime::Runtime,
+    pub(crate) client: Client,
+    #[cfg(feature = "blocking")]
+    pub(crate) blocking: HttpApiClient,
+}
+
+#[cfg(feature = "reqwest")]
+impl TestClients {
+    pub(crate) fn new(url: String) -> TestClients {
+        TestClients::with_config(url, ClientConfig::default)
+    }
+
+    pub(crate) fn with_config(url: String, config: impl Fn() -> ClientConfig) -> TestClients {
+        let credentials = || Credentials::UserAuthToken {
+            token: "dummy".into(),
+        };
+        TestClients::with_credentials(url, credentials, config)
+    }
+
+    /// Builds each client with its own credentials provider and config.
+    pub(crate) fn with_credentials<P: CredentialsProvider + 'static>(
+        url: String,
+        credentials: impl Fn() -> P,
+        config: impl Fn() -> ClientConfig,
+    ) -> TestClients {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let environment = Environment::Custom(url);
+        TestClients {
+            runtime,
+            client: Client::new(credentials(), config(), environment.clone()).unwrap(),
+            #[cfg(feature = "blocking")]
+            blocking: HttpApiClient::new(credentials(), config(), environment).unwrap(),
+        }
+    }
+
+    /// Sends the request with each client, through the `ApiClient` and `BlockingApiClient`
+    /// traits, returning one response per client.
+    pub(crate) fn request<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+    ) -> Vec<ApiResponse<Endpoint::ResponseType>>
+    where
+        Endpoint: EndpointSpec + Send + Sync,
+    {
+        #[allow(unused_mut)]
+        let mut responses = vec![self
+            .runtime
+            .block_on(ApiClient::request(&self.client, endpoint))];
+        #[cfg(feature = "blocking")]
+        responses.push(BlockingApiClient::request(&self.blocking, endpoint));
+        responses
+    }
+
+    /// Like `request`, with options which only apply to this request.
+    pub(crate) fn request_with<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+        options: &RequestOptions,
+    ) -> Vec<ApiResponse<Endpoint::ResponseType>>
+    where
+        Endpoint: EndpointSpec + Send + Sync,
+    {
+        #[allow(unused_mut)]
+        let mut responses = vec![self
+            .runtime
+            .block_on(self.client.request_with(endpoint, options))];
+        #[cfg(feature = "blocking")]
+        responses.push(self.blocking.request_with(endpoint, options));
+        responses
+    }
+
+    /// Like `request`, with the status and headers of each response.
+    pub(crate) fn request_meta<Endpoint>(
+        &self,
+        endpoint: &Endpoint,
+    ) -> Vec<ApiResponse<Response<Endpoint::ResponseType>>>
+    where
+        Endpoint: EndpointSpec + Send + Sync,
+    {
+        #[allow(unused_mut)]
+        let mut responses = vec![self
+            .runtime
+            .block_on(self.client.request_with_meta(endpoint))];
+        #[cfg(feature = "blocking")]
+        responses.push(self.blocking.request_with_meta(endpoint));
+        responses
+    }
+}
+
+pub(crate) fn fast_retry_config() -> ClientConfig {
+    ClientConfig {
+        retry_policy: Some(RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: std::time::Duration::from_millis(1),
+            max_backoff: std::time::Duration::from_millis(5),
+            retry_non_idempotent: false,
+        }),
+        ..ClientConfig::default()
+    }
+}
@@ -0,0 +1,264 @@
+//! `tracing` instrumentation of API requests, shared by the async and blocking clients.
+//!
+//! With the `tracing` feature, each request runs in a `cloudflare.request` span, which records:
+//! - `http.method`: the HTTP method,
+//! - `http.path`: the path of the endpoint, with identifiers replaced by `{id}`,
+//! - `endpoint`: the type of the endpoint (e.g. `cloudflare::endpoints::dns::dns::ListDnsRecords`),
+//! - `http.status_code`, `http.response_size` and `cf_ray`, once a response is received (the
+//!   size is not recorded for streamed responses),
+//! - `duration_ms`: the time taken by the request, including retries and parsing.
+//!
+//! Without the feature, none of this does anything.
+use crate::framework::endpoint::EndpointSpec;
+use bytes::Bytes;
+use std::future::Future;
+
+#[cfg(feature = "tracing")]
+pub(crate) use enabled::RequestSpan;
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) use disabled::RequestSpan;
+
+/// Turns the path of an endpoint into a template, suitable to group requests by endpoint.
+///
+/// `EndpointSpec::path` returns the final path, so identifiers are recognized by their format:
+/// 32 hex digits (zones, accounts, records, ...), UUIDs and numbers.
+#[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+fn path_template(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            if is_identifier(segment) {
+                "{id}"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn is_identifier(segment: &str) -> bool {
+    let is_hex = |s: &str| s.bytes().all(|b| b.is_ascii_hexdigit());
+    let is_uuid = segment.len() == 36
+        && segment.split('-').map(str::len).eq([8, 4, 4, 4, 12])
+        && is_hex(&segment.replace('-', ""));
+    let is_number = !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit());
+    (segment.len() == 32 && is_hex(segment)) || is_uuid || is_number
+}
+
+#[cfg(feature = "tracing")]
+mod enabled {
+    use super::*;
+    use tracing::field::Empty;
+    use tracing::Instrument;
+
+    pub(crate) struct RequestSpan {
+        span: tracing::Span,
+        #[cfg(not(target_arch = "wasm32"))]
+        start: std::time::Instant,
+    }
+
+    impl RequestSpan {
+        pub(crate) fn new<Endpoint: EndpointSpec>(endpoint: &Endpoint) -> RequestSpan {
+            let span = tracing::info_span!(
+                "cloudflare.request",
+                http.method = %endpoint.method(),
+                http.path = %path_template(&endpoint.path()),
+                endpoint = std::any::type_name::<Endpoint>(),
+                http.status_code = Empty,
+                http.response_size = Empty,
+                cf_ray = Empty,
+                duration_ms = Empty,
+            );
+            RequestSpan {
+                span,
+                #[cfg(not(target_arch = "wasm32"))]
+                start: std::time::Instant::now(),
+            }
+        }
+
+        /// Runs `f` in the span.
+        #[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+        pub(crate) fn in_scope<T>(&self, f: impl FnOnce() -> T) -> T {
+            self.span.in_scope(f)
+        }
+
+        /// Runs `future` in the span.
+        pub(crate) fn instrument<F: Future>(&self, future: F) -> impl Future<Output = F::Output> {
+            future.instrument(self.span.clone())
+        }
+
+        /// Records the response, which is about to be parsed.
+        pub(crate) fn record_response(&self, response: &http::Response<Bytes>) {
+            self.record_head(response);
+            self.span
+                .record("http.response_size", response.body().len());
+        }
+
+        /// Records the status and headers of a response, whose body is streamed.
+        pub(crate) fn record_head<B>(&self, response: &http::Response<B>) {
+            let span = &self.span;
+            span.record("http.status_code", response.status().as_u16());
+            if let Some(ray) = response.headers().get("cf-ray") {
+                span.record("cf_ray", String::from_utf8_lossy(ray.as_bytes()).as_ref());
+            }
+        }
+    }
+
+    impl Drop for RequestSpan {
+        fn drop(&mut self) {
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                let duration = self.start.elapsed().as_millis() as u64;
+                self.span.record("duration_ms", duration);
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+mod disabled {
+    use super::*;
+
+    pub(crate) struct RequestSpan;
+
+    impl RequestSpan {
+        pub(crate) fn new<Endpoint: EndpointSpec>(_endpoint: &Endpoint) -> RequestSpan {
+            RequestSpan
+        }
+
+        #[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+        pub(crate) fn in_scope<T>(&self, f: impl FnOnce() -> T) -> T {
+            f()
+        }
+
+        pub(crate) fn instrument<F: Future>(&self, future: F) -> F {
+            future
+        }
+
+        pub(crate) fn record_response(&self, _response: &http::Response<Bytes>) {}
+
+        pub(crate) fn record_head<B>(&self, _response: &http::Response<B>) {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(all(feature = "tracing", feature = "reqwest"))]
+    use crate::framework::client::test_support::*;
+    #[cfg(all(feature = "tracing", feature = "reqwest"))]
+    use crate::framework::response::ApiFailure;
+    #[cfg(all(feature = "tracing", feature = "reqwest"))]
+    use mockito::Server;
+    #[cfg(all(feature = "tracing", feature = "reqwest"))]
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn templates_identifiers() {
+        assert_eq!(
+            path_template("zones/023e105f4ecef8ad9ca31a8372d0c353/dns_records"),
+            "zones/{id}/dns_records"
+        );
+        assert_eq!(
+            path_template("accounts/abc/cfd_tunnel/f70ff985-a4ef-4643-bbbc-4a0ed4fc8415"),
+            "accounts/abc/cfd_tunnel/{id}"
+        );
+        assert_eq!(
+            path_template("accounts/abc/workers/scripts/my-worker/schedules"),
+            "accounts/abc/workers/scripts/my-worker/schedules"
+        );
+        assert_eq!(path_template("user/tokens/12"), "user/tokens/{id}");
+    }
+
+    /// Records the fields of spans and events, as `name=value` strings.
+    #[cfg(all(feature = "tracing", feature = "reqwest"))]
+    #[derive(Clone, Default)]
+    struct TracingRecorder(Arc<Mutex<Vec<String>>>);
+
+    #[cfg(all(feature = "tracing", feature = "reqwest"))]
+    impl tracing::field::Visit for &TracingRecorder {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            let record = format!("{}={value:?}", field.name());
+            self.0.lock().unwrap().push(record);
+        }
+
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{}={value}", field.name()));
+        }
+    }
+
+    #[cfg(all(feature = "tracing", feature = "reqwest"))]
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for TracingRecorder {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            _: &tracing::span::Id,
+            _: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            attrs.record(&mut &*self);
+        }
+
+        fn on_record(
+            &self,
+            _: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            _: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            values.record(&mut &*self);
+        }
+
+        fn on_event(
+            &self,
+            event: &tracing::Event<'_>,
+            _: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            event.record(&mut &*self);
+        }
+    }
+
+    /// Test that both clients trace requests and JSON parsing failures.
+    #[cfg(all(feature = "tracing", feature = "reqwest"))]
+    #[test]
+    fn test_clients_trace_requests() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let mut server = Server::new();
+        server
+            .mock("GET", "/dummy/json")
+            .with_status(200)
+            .with_header("cf-ray", "8d7c3ab6cd3f9b1a-LHR")
+            .with_body("not json")
+            .create();
+
+        let recorder = TracingRecorder::default();
+        let subscriber = tracing_subscriber::registry().with(recorder.clone());
+        let responses = tracing::subscriber::with_default(subscriber, || {
+            TestClients::new(server.url()).request(&DummyJsonEndpoint)
+        });
+
+        let records = recorder.0.lock().unwrap();
+        for response in &responses {
+            assert!(matches!(response, Err(ApiFailure::Deserialize(_))));
+        }
+        for expected in [
+            "http.method=GET",
+            "http.path=/dummy/json",
+            "http.status_code=200",
+            "http.response_size=8",
+            "cf_ray=8d7c3ab6cd3f9b1a-LHR",
+            "message=Failed to parse a Cloudflare API response",
+        ] {
+            let count = records.iter().filter(|record| *record == expected).count();
+            assert_eq!(count, responses.len(), "{expected} in {records:?}");
+        }
+        let durations = records
+            .iter()
+            .filter(|record| record.starts_with("duration_ms="))
+            .count();
+        assert_eq!(durations, responses.len());
+    }
+}
@@ -0,0 +1,630 @@
+//! The HTTP layer used by the clients to send requests.
+//!
+//! By default, requests are sent with `reqwest`. Other HTTP stacks can be plugged in by
+//! implementing [`Transport`] (or [`BlockingTransport`] for the blocking client) and passing it to
+//! `Client::new_with_transport`.
+#[cfg(feature = "reqwest")]
+use crate::framework::client::options::RequestTimeout;
+use crate::framework::endpoint::{BodySource, CHUNK_SIZE};
+use bytes::Bytes;
+use futures_util::stream::{self, Stream, StreamExt, TryStreamExt};
+#[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+use http::header::CONTENT_LENGTH;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::pin::Pin;
+#[cfg(feature = "reqwest")]
+use std::time::Duration;
+
+/// The future returned by [`Transport::send`].
+#[cfg(not(target_arch = "wasm32"))]
+pub type TransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<http::Response<Bytes>, TransportError>> + Send + 'a>>;
+
+/// The future returned by [`Transport::send`].
+// Futures are not `Send` in wasm.
+#[cfg(target_arch = "wasm32")]
+pub type TransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<http::Response<Bytes>, TransportError>> + 'a>>;
+
+/// The body of a streamed response, as it is received.
+#[cfg(not(target_arch = "wasm32"))]
+pub type BodyStream = Pin<Box<dyn Stream<Item = Result<Bytes, TransportError>> + Send>>;
+
+/// The body of a streamed response, as it is received.
+#[cfg(target_arch = "wasm32")]
+pub type BodyStream = Pin<Box<dyn Stream<Item = Result<Bytes, TransportError>>>>;
+
+/// The future returned by [`Transport::send_streaming`].
+#[cfg(not(target_arch = "wasm32"))]
+pub type StreamingTransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<http::Response<BodyStream>, TransportError>> + Send + 'a>>;
+
+/// The future returned by [`Transport::send_streaming`].
+#[cfg(target_arch = "wasm32")]
+pub type StreamingTransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<http::Response<BodyStream>, TransportError>> + 'a>>;
+
+/// The body of a streamed response, for the blocking client.
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+pub type BodyReader = Box<dyn Read + Send>;
+
+/// Sends HTTP requests for the async client.
+///
+/// The request is complete: its URL, headers (including authentication) and body are set.
+/// The transport only has to send it and read the whole response, within the [`RequestTimeout`]
+/// in its extensions if there is one.
+/// Non-2XX responses are not errors for the transport; they are handled by the client.
+///
+/// [`RequestTimeout`]: crate::framework::client::options::RequestTimeout
+pub trait Transport: Send + Sync {
+    fn send(&self, request: http::Request<Bytes>) -> TransportFuture<'_>;
+
+    /// Sends a request whose body is streamed. The body of `request` itself is empty.
+    ///
+    /// By default, the whole body is read into memory and sent with `send`.
+    fn send_upload(&self, request: http::Request<Bytes>, body: UploadBody) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let body = body.into_bytes().await?;
+            self.send(request.map(|_| body)).await
+        })
+    }
+
+    /// Sends the request, and returns its response as soon as its headers are received.
+    ///
+    /// By default, the whole response is read by `send`, and its body is streamed in one chunk.
+    fn send_streaming(&self, request: http::Request<Bytes>) -> StreamingTransportFuture<'_> {
+        Box::pin(async move {
+            let response = self.send(request).await?;
+            Ok(response.map(|body| Box::pin(single_chunk(body)) as BodyStream))
+        })
+    }
+}
+
+/// Sends HTTP requests for the blocking client. See [`Transport`].
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+pub trait BlockingTransport: Send + Sync {
+    fn send(&self, request: http::Request<Bytes>) -> Result<http::Response<Bytes>, TransportError>;
+
+    /// Sends a request whose body is streamed. The body of `request` itself is empty.
+    ///
+    /// By default, the whole body is read into memory and sent with `send`.
+    fn send_upload(
+        &self,
+        request: http::Request<Bytes>,
+        body: UploadBody,
+    ) -> Result<http::Response<Bytes>, TransportError> {
+        let mut buffer = Vec::new();
+        body.into_reader()
+            .read_to_end(&mut buffer)
+            .map_err(io_error)?;
+        self.send(request.map(|_| Bytes::from(buffer)))
+    }
+
+    /// Sends the request, and returns its response as soon as its headers are received.
+    ///
+    /// By default, the whole response is read by `send`, and its body is read from memory.
+    fn send_streaming(
+        &self,
+        request: http::Request<Bytes>,
+    ) -> Result<http::Response<BodyReader>, TransportError> {
+        let response = self.send(request)?;
+        Ok(response.map(|body| Box::new(std::io::Cursor::new(body)) as BodyReader))
+    }
+}
+
+fn single_chunk(body: Bytes) -> impl Stream<Item = Result<Bytes, TransportError>> {
+    stream::iter((!body.is_empty()).then_some(Ok(body)))
+}
+
+/// The body of a request which is read as it is sent, instead of being held in memory.
+///
+/// It is made of the chunks of a `StreamBody`, or of the parts of a multipart body which includes
+/// files.
+pub struct UploadBody {
+    segments: Vec<Segment>,
+}
+
+pub(crate) enum Segment {
+    Bytes(Bytes),
+    File(PathBuf),
+    Source(BodySource, Option<u64>),
+}
+
+impl UploadBody {
+    pub(crate) fn new(segments: Vec<Segment>) -> UploadBody {
+        UploadBody { segments }
+    }
+
+    /// The length of the body, if the length of each of its parts is known.
+    pub fn content_length(&self) -> Option<u64> {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Bytes(bytes) => Some(bytes.len() as u64),
+                Segment::File(path) => std::fs::metadata(path).ok().map(|metadata| metadata.len()),
+                Segment::Source(_, content_length) => *content_length,
+            })
+            .sum()
+    }
+
+    /// Streams the body.
+    ///
+    /// Readers and files are read on the blocking thread pool of tokio when the stream is polled
+    /// within a tokio runtime, so that slow reads don't stall the runtime. Otherwise, they are
+    /// read in the task polling the stream.
+    pub fn into_stream(self) -> BodyStream {
+        let segments = stream::iter(self.segments).flat_map(|segment| match segment {
+            Segment::Bytes(bytes) => single_chunk(bytes).boxed(),
+            Segment::File(path) => read_chunks(LazyFile::new(path)).boxed(),
+            Segment::Source(BodySource::Stream(stream), _) => stream.map_err(io_error).boxed(),
+            Segment::Source(BodySource::Reader(reader), _) => read_chunks(reader).boxed(),
+            Segment::Source(BodySource::Sent, _) => {
+                stream::iter([Err(io_error(already_sent()))]).boxed()
+            }
+        });
+        Box::pin(segments)
+    }
+
+    /// Reads the whole body.
+    pub(crate) async fn into_bytes(self) -> Result<Bytes, TransportError> {
+        let body = self
+            .into_stream()
+            .try_fold(Vec::new(), |mut body, chunk| async move {
+                body.extend_from_slice(&chunk);
+                Ok(body)
+            })
+            .await?;
+        Ok(Bytes::from(body))
+    }
+
+    /// Reads the whole body on the current thread. Streams fail, as they need an async runtime.
+    pub(crate) fn read_to_vec(self) -> Result<Vec<u8>, TransportError> {
+        let mut body = Vec::new();
+        for segment in self.segments {
+            match segment {
+                Segment::Bytes(bytes) => body.extend_from_slice(&bytes),
+                Segment::File(path) => {
+                    LazyFile::new(path)
+                        .read_to_end(&mut body)
+                        .map_err(io_error)?;
+                }
+                Segment::Source(BodySource::Reader(mut reader), _) => {
+                    reader.read_to_end(&mut body).map_err(io_error)?;
+                }
+                Segment::Source(BodySource::Stream(_), _) => {
+                    let error = "A streamed body can only be read by a client";
+                    return Err(io_error(io::Error::new(io::ErrorKind::Unsupported, error)));
+                }
+                Segment::Source(BodySource::Sent, _) => return Err(io_error(already_sent())),
+            }
+        }
+        Ok(body)
+    }
+
+    /// Returns a reader of the body, for the blocking client.
+    ///
+    /// Streams are waited for on the current thread, so they must not require an async runtime.
+    #[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+    pub fn into_reader(self) -> BodyReader {
+        self.segments
+            .into_iter()
+            .map(|segment| -> BodyReader {
+                match segment {
+                    Segment::Bytes(bytes) => Box::new(io::Cursor::new(bytes)),
+                    Segment::File(path) => Box::new(LazyFile::new(path)),
+                    Segment::Source(BodySource::Stream(stream), _) => {
+                        Box::new(StreamReader::new(stream))
+                    }
+                    Segment::Source(BodySource::Reader(reader), _) => reader,
+                    Segment::Source(BodySource::Sent, _) => Box::new(SentReader),
+                }
+            })
+            .fold(Box::new(io::empty()), |body, segment| {
+                Box::new(body.chain(segment))
+            })
+    }
+}
+
+fn read_chunks<R>(reader: R) -> impl Stream<Item = Result<Bytes, TransportError>> + Send
+where
+    R: Read + Send + 'static,
+{
+    stream::try_unfold(reader, |reader| async move {
+        let (chunk, reader) = read_chunk(reader).await?;
+        Ok((!chunk.is_empty()).then_some((chunk, reader)))
+    })
+}
+
+/// Reads the next chunk of `reader`, which is empty at the end of the reader. See
+/// `UploadBody::into_stream`.
+async fn read_chunk<R>(mut reader: R) -> Result<(Bytes, R), TransportError>
+where
+    R: Read + Send + 'static,
+{
+    let read = move || {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let read = reader.read(&mut chunk)?;
+        chunk.truncate(read);
+        Ok((Bytes::from(chunk), reader))
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+        let result = runtime.spawn_blocking(read).await.map_err(io::Error::other);
+        return result.and_then(|result| result).map_err(io_error);
+    }
+    read().map_err(io_error)
+}
+
+fn already_sent() -> io::Error {
+    io::Error::other("The streamed body was already sent")
+}
+
+fn io_error(error: io::Error) -> TransportError {
+    TransportError::new(TransportErrorKind::Other, error)
+}
+
+/// A file, opened when it is first read.
+struct LazyFile {
+    path: PathBuf,
+    file: Option<std::fs::File>,
+}
+
+impl LazyFile {
+    fn new(path: PathBuf) -> LazyFile {
+        LazyFile { path, file: None }
+    }
+}
+
+impl Read for LazyFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => self.file.insert(std::fs::File::open(&self.path)?),
+        };
+        file.read(buf)
+    }
+}
+
+/// A reader which fails, for bodies which were already sent.
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+struct SentReader;
+
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+impl Read for SentReader {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(already_sent())
+    }
+}
+
+/// Reads a stream, waiting for each chunk on the current thread.
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+struct StreamReader {
+    stream: Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>,
+    chunk: Bytes,
+}
+
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+impl StreamReader {
+    fn new(stream: Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>) -> StreamReader {
+        StreamReader {
+            stream,
+            chunk: Bytes::new(),
+        }
+    }
+}
+
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+impl Read for StreamReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.chunk.is_empty() {
+            match futures_executor::block_on(self.stream.next()) {
+                Some(chunk) => self.chunk = chunk?,
+                None => return Ok(0),
+            }
+        }
+        let read = buf.len().min(self.chunk.len());
+        buf[..read].copy_from_slice(&self.chunk.split_to(read));
+        Ok(read)
+    }
+}
+
+/// A request could not be sent, or its response could not be received.
+#[derive(Debug)]
+pub struct TransportError {
+    kind: TransportErrorKind,
+    source: Box<dyn Error + Send + Sync>,
+}
+
+/// What went wrong in a [`TransportError`]. Connection errors and timeouts may be retried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TransportErrorKind {
+    /// The connection to the API could not be established.
+    Connect,
+    /// The request did not complete in time.
+    Timeout,
+    /// Any other error.
+    Other,
+}
+
+impl TransportError {
+    pub fn new(kind: TransportErrorKind, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        TransportError {
+            kind,
+            source: source.into(),
+        }
+    }
+
+    pub fn kind(&self) -> TransportErrorKind {
+        self.kind
+    }
+
+    pub fn is_connect(&self) -> bool {
+        self.kind == TransportErrorKind::Connect
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        self.kind == TransportErrorKind::Timeout
+    }
+
+    /// Returns the error raised by the underlying HTTP stack.
+    pub fn into_inner(self) -> Box<dyn Error + Send + Sync> {
+        self.source
+    }
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Error for TransportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Error> for TransportError {
+    fn from(error: reqwest::Error) -> Self {
+        let kind = if error.is_timeout() {
+            TransportErrorKind::Timeout
+        } else if error.is_connect() {
+            TransportErrorKind::Connect
+        } else {
+            TransportErrorKind::Other
+        };
+        TransportError::new(kind, error)
+    }
+}
+
+/// Converts a request for `reqwest`, applying its `RequestTimeout`.
+#[cfg(feature = "reqwest")]
+fn reqwest_request<B, R>(request: http::Request<B>) -> Result<R, TransportError>
+where
+    http::Request<B>: TryInto<R, Error = reqwest::Error>,
+    R: ReqwestRequest,
+{
+    let timeout = request.extensions().get::<RequestTimeout>().copied();
+    let mut request = request.try_into()?;
+    if let Some(RequestTimeout(timeout)) = timeout {
+        request.set_timeout(timeout);
+    }
+    Ok(request)
+}
+
+/// The requests of the async and blocking `reqwest` clients.
+#[cfg(feature = "reqwest")]
+trait ReqwestRequest {
+    fn set_timeout(&mut self, timeout: Duration);
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestRequest for reqwest::Request {
+    // There are no timeouts in wasm.
+    #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+    fn set_timeout(&mut self, timeout: Duration) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            *self.timeout_mut() = Some(timeout);
+        }
+    }
+}
+
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+impl ReqwestRequest for reqwest::blocking::Request {
+    fn set_timeout(&mut self, timeout: Duration) {
+        *self.timeout_mut() = Some(timeout);
+    }
+}
+
+/// The default [`Transport`], backed by a `reqwest::Client`.
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Debug, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestTransport {
+    pub fn new(client: reqwest::Client) -> Self {
+        ReqwestTransport { client }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestTransport {
+    async fn execute<B>(
+        &self,
+        request: http::Request<B>,
+    ) -> Result<http::Response<Bytes>, TransportError>
+    where
+        B: Into<reqwest::Body>,
+    {
+        let response = self.client.execute(reqwest_request(request)?).await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let mut response = http::Response::new(response.bytes().await?);
+        *response.status_mut() = status;
+        *response.headers_mut() = headers;
+        Ok(response)
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Client> for ReqwestTransport {
+    fn from(client: reqwest::Client) -> Self {
+        ReqwestTransport::new(client)
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Transport for ReqwestTransport {
+    fn send(&self, request: http::Request<Bytes>) -> TransportFuture<'_> {
+        Box::pin(self.execute(request))
+    }
+
+    // Streamed request bodies are not supported by reqwest in wasm, where the default is used.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn send_upload(&self, request: http::Request<Bytes>, body: UploadBody) -> TransportFuture<'_> {
+        let content_length = body.content_length();
+        let mut request = request.map(|_| reqwest::Body::wrap_stream(body.into_stream()));
+        if let Some(content_length) = content_length {
+            request
+                .headers_mut()
+                .insert(CONTENT_LENGTH, content_length.into());
+        }
+        Box::pin(self.execute(request))
+    }
+
+    // `reqwest::Response::chunk` is not available in wasm, where the default is used.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn send_streaming(&self, request: http::Request<Bytes>) -> StreamingTransportFuture<'_> {
+        Box::pin(async move {
+            let response = self.client.execute(reqwest_request(request)?).await?;
+            let status = response.status();
+            let headers = response.headers().clone();
+            let body = stream::try_unfold(response, |mut response| async move {
+                let chunk = response.chunk().await?;
+                Ok(chunk.map(|chunk| (chunk, response)))
+            });
+            let mut response = http::Response::new(Box::pin(body) as BodyStream);
+            *response.status_mut() = status;
+            *response.headers_mut() = headers;
+            Ok(response)
+        })
+    }
+}
+
+/// The default [`BlockingTransport`], backed by a `reqwest::blocking::Client`.
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+#[derive(Clone, Debug)]
+pub struct ReqwestBlockingTransport {
+    client: reqwest::blocking::Client,
+}
+
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+impl ReqwestBlockingTransport {
+    pub fn new(client: reqwest::blocking::Client) -> Self {
+        ReqwestBlockingTransport { client }
+    }
+}
+
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+impl ReqwestBlockingTransport {
+    fn execute<B>(&self, request: http::Request<B>) -> Result<http::Response<Bytes>, TransportError>
+    where
+        B: Into<reqwest::blocking::Body>,
+    {
+        let response = self.client.execute(reqwest_request(request)?)?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let mut response = http::Response::new(response.bytes()?);
+        *response.status_mut() = status;
+        *response.headers_mut() = headers;
+        Ok(response)
+    }
+}
+
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+impl From<reqwest::blocking::Client> for ReqwestBlockingTransport {
+    fn from(client: reqwest::blocking::Client) -> Self {
+        ReqwestBlockingTransport::new(client)
+    }
+}
+
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+impl BlockingTransport for ReqwestBlockingTransport {
+    fn send(&self, request: http::Request<Bytes>) -> Result<http::Response<Bytes>, TransportError> {
+        self.execute(request)
+    }
+
+    fn send_upload(
+        &self,
+        request: http::Request<Bytes>,
+        body: UploadBody,
+    ) -> Result<http::Response<Bytes>, TransportError> {
+        let body = match body.content_length() {
+            Some(content_length) => {
+                reqwest::blocking::Body::sized(body.into_reader(), content_length)
+            }
+            None => reqwest::blocking::Body::new(body.into_reader()),
+        };
+        self.execute(request.map(|_| body))
+    }
+
+    fn send_streaming(
+        &self,
+        request: http::Request<Bytes>,
+    ) -> Result<http::Response<BodyReader>, TransportError> {
+        let response = self.client.execute(reqwest_request(request)?)?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let mut response = http::Response::new(Box::new(response) as BodyReader);
+        *response.status_mut() = status;
+        *response.headers_mut() = headers;
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::framework::auth::Credentials;
+    use crate::framework::client::async_api::Client;
+    use crate::framework::client::test_support::*;
+    use crate::framework::client::ClientConfig;
+    use crate::framework::Environment;
+
+    /// Test that requests are sent through a custom transport.
+    #[tokio::test]
+    async fn test_custom_transport() {
+        let transport = FakeTransport {
+            response: Some((200, r#"{"result": null, "success": true}"#)),
+            ..FakeTransport::default()
+        };
+        let client = Client::new_with_transport(
+            transport.clone(),
+            Credentials::UserAuthToken {
+                token: "dummy".into(),
+            },
+            ClientConfig::default(),
+            Environment::Custom("https://example.com/v4/".into()),
+        );
+
+        let response = client.request(&DummyJsonRequestEndpoint).await;
+
+        assert!(response.is_ok());
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method(), http::Method::POST);
+        assert_eq!(requests[0].uri(), "https://example.com/v4/dummy/json");
+        assert_eq!(requests[0].headers()["authorization"], "Bearer dummy");
+        assert_eq!(requests[0].headers()["content-type"], "application/json");
+        assert_eq!(requests[0].body(), r#"{"key":"value"}"#);
+    }
+}
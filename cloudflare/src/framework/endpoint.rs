@@ -1,7 +1,15 @@
-use crate::framework::response::ApiResult;
+use crate::framework::response::{ApiResult, ResponseKind, ResultInfo};
 use crate::framework::Environment;
+use bytes::Bytes;
+use futures_util::io::{AsyncRead, AsyncReadExt};
+use futures_util::stream::{self, Stream};
 use serde::Serialize;
 use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Mutex;
 use url::Url;
 
 pub use http::Method;
@@ -10,26 +18,198 @@ pub(crate) use spec::EndpointSpec;
 
 pub enum RequestBody<'a> {
     Json(String),
+    /// A value which is serialized to JSON when the request is built. If it cannot be serialized,
+    /// the client returns an error instead of sending the request.
+    Serialize(&'a dyn JsonBody),
     Raw(Vec<u8>),
     MultiPart(&'a dyn MultipartBody),
+    /// A body which is read as the request is sent, instead of being held in memory.
+    Stream(&'a StreamBody),
+}
+
+impl RequestBody<'_> {
+    /// The default MIME type of the body. Multipart bodies are sent with their boundary.
+    pub(crate) fn content_type(&self) -> &'static str {
+        match self {
+            RequestBody::Json(_) | RequestBody::Serialize(_) => "application/json",
+            RequestBody::Raw(_) | RequestBody::Stream(_) => "application/octet-stream",
+            RequestBody::MultiPart(_) => "multipart/form-data",
+        }
+    }
 }
 
 pub enum MultipartPart {
     Text(String),
     Bytes(Vec<u8>),
+    /// A file, which is read as the request is sent.
+    File(FilePart),
+}
+
+/// A file sent as a part of a multipart body.
+#[derive(Clone, Debug)]
+pub struct FilePart {
+    pub path: PathBuf,
+    /// The name of the file given to the API. Defaults to the name of the file on disk.
+    pub filename: Option<String>,
+    /// The content type of the part. Defaults to none, which the API treats as
+    /// `text/plain`.
+    pub content_type: Option<String>,
+}
+
+impl FilePart {
+    pub fn new(path: impl Into<PathBuf>) -> FilePart {
+        let path = path.into();
+        let filename = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        FilePart {
+            path,
+            filename,
+            content_type: None,
+        }
+    }
+
+    pub fn content_type(mut self, content_type: impl Into<String>) -> FilePart {
+        self.content_type = Some(content_type.into());
+        self
+    }
+}
+
+/// The size of the chunks read from readers and files.
+pub(crate) const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A request body which is read as the request is sent, instead of being held in memory.
+///
+/// Both clients accept every kind of source, but the async client is best used with streams
+/// and async readers, and the blocking client with readers: the async client reads from readers
+/// and files in its task, and the blocking client waits for streams on its thread.
+///
+/// The body can only be sent once. Requests with a streamed body are not retried, and sending the
+/// same endpoint again fails.
+pub struct StreamBody {
+    source: Mutex<BodySource>,
+    content_length: Option<u64>,
+}
+
+pub(crate) enum BodySource {
+    Stream(Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>),
+    Reader(Box<dyn Read + Send>),
+    /// The body was already sent.
+    Sent,
+}
+
+impl StreamBody {
+    /// Streams the chunks of `stream`. If `content_length` is known, it is sent to the API, which
+    /// may require it.
+    pub fn new<S>(stream: S, content_length: Option<u64>) -> StreamBody
+    where
+        S: Stream<Item = io::Result<Bytes>> + Send + 'static,
+    {
+        StreamBody::from_source(BodySource::Stream(Box::pin(stream)), content_length)
+    }
+
+    /// Streams the content of an async reader.
+    pub fn from_async_reader<R>(reader: R, content_length: Option<u64>) -> StreamBody
+    where
+        R: AsyncRead + Send + 'static,
+    {
+        let chunks = stream::try_unfold(Box::pin(reader), |mut reader| async move {
+            let mut chunk = vec![0; CHUNK_SIZE];
+            let read = reader.read(&mut chunk).await?;
+            chunk.truncate(read);
+            Ok((read > 0).then(|| (Bytes::from(chunk), reader)))
+        });
+        StreamBody::new(chunks, content_length)
+    }
+
+    /// Streams the content of a reader.
+    pub fn from_reader<R>(reader: R, content_length: Option<u64>) -> StreamBody
+    where
+        R: Read + Send + 'static,
+    {
+        StreamBody::from_source(BodySource::Reader(Box::new(reader)), content_length)
+    }
+
+    /// Streams the content of a file, whose size is the content length.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<StreamBody> {
+        let file = std::fs::File::open(path)?;
+        let content_length = file.metadata()?.len();
+        Ok(StreamBody::from_reader(file, Some(content_length)))
+    }
+
+    fn from_source(source: BodySource, content_length: Option<u64>) -> StreamBody {
+        StreamBody {
+            source: Mutex::new(source),
+            content_length,
+        }
+    }
+
+    pub fn content_length(&self) -> Option<u64> {
+        self.content_length
+    }
+
+    /// Takes the source of the body, to send it.
+    pub(crate) fn take(&self) -> BodySource {
+        let mut source = self
+            .source
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        std::mem::replace(&mut *source, BodySource::Sent)
+    }
+}
+
+impl fmt::Debug for StreamBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamBody")
+            .field("content_length", &self.content_length)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A value which can be sent as a JSON body, see `RequestBody::Serialize`.
+///
+/// Implemented for every type which implements `Serialize`.
+pub trait JsonBody {
+    fn to_json(&self) -> serde_json::Result<Vec<u8>>;
+}
+
+impl<T: Serialize + ?Sized> JsonBody for T {
+    fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec(self)
+    }
+}
+
+/// An endpoint which could not be built into a request, or which breaks a documented limit of the
+/// API. The request is not sent.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum RequestError {
+    /// The body of the request could not be serialized.
+    #[error("could not serialize the request body: {0}")]
+    Serialize(#[from] serde_json::Error),
+    /// The URL of the request could not be built from the environment and the path.
+    #[error("invalid request URL: {0}")]
+    Url(#[from] url::ParseError),
+    /// A parameter of the endpoint breaks a documented limit of the API.
+    #[error("invalid {field}: {reason}")]
+    Invalid { field: &'static str, reason: String },
+}
+
+impl RequestError {
+    pub fn invalid(field: &'static str, reason: impl Into<String>) -> RequestError {
+        RequestError::Invalid {
+            field,
+            reason: reason.into(),
+        }
+    }
 }
 
 /// Helper trait for endpoints that require a multipart body.
 ///
-/// Mainly exists to allow for client-agnostic multipart body implementations, until reqwest has a
-/// conversion between blocking::multipart::Form/Part and async_impl::multipart::Form/Part.
+/// The parts are encoded as `multipart/form-data` by the client, independently of the transport.
 pub trait MultipartBody {
     /// Returns a list of parts to be included in a multipart request.
     /// Each part is a tuple of the part name and the part data.
-    //
-    // Client-agnostic implementation, because of the non-interoperability
-    // between reqwest's blocking::multipart::Form/Part and async_impl::multipart::Form/Part.
-    // Refactor this when reqwest has some sort of conversion between the two.
     fn parts(&self) -> Vec<(String, MultipartPart)>;
 }
 
@@ -41,9 +221,6 @@ pub mod spec {
     ///
     /// If the request succeeds, the call will resolve to a `ResultType`.
     pub trait EndpointSpec {
-        /// If the body of the response is raw bytes (Vec<u8>), set this to `true`. Defaults to `false`.
-        const IS_RAW_BODY: bool = false;
-
         /// The JSON response type for this endpoint, if any.
         ///
         /// For endpoints that return either raw bytes or nothing, this should be `()`.
@@ -53,7 +230,11 @@ pub mod spec {
         /// For endpoints that return raw bytes, this should be `Vec<u8>`.
         ///
         /// For endpoints that return JSON, this should be `ApiSuccess<Self::JsonResponse>`.
-        type ResponseType;
+        ///
+        /// For endpoints that return an empty body, this should be `()`.
+        ///
+        /// The bound on `ResponseKind::Json` enforces these rules.
+        type ResponseType: ResponseKind<Json = Self::JsonResponse>;
 
         /// The HTTP Method used for this endpoint (e.g. GET, PATCH, DELETE)
         fn method(&self) -> Method;
@@ -77,26 +258,45 @@ pub mod spec {
             None
         }
 
+        /// Checks the parameters of the endpoint against the documented limits of the API, before
+        /// the request is built. Defaults to accepting every endpoint.
+        ///
+        /// The clients do not send requests for endpoints which fail this check.
+        fn validate(&self) -> Result<(), RequestError> {
+            Ok(())
+        }
+
+        /// The maximum length of the body of the request, in bytes, as documented by the API.
+        /// Defaults to no limit.
+        ///
+        /// The clients check the length once the body is built, and do not send longer requests.
+        fn max_body_len(&self) -> Option<usize> {
+            None
+        }
+
         /// Builds and returns a formatted full URL, including query, for the endpoint.
         ///
         /// Implementors should generally not override this.
-        fn url(&self, environment: &Environment) -> Url {
-            let mut url = Url::from(environment).join(&self.path()).unwrap();
+        fn url(&self, environment: &Environment) -> Result<Url, url::ParseError> {
+            let mut url = environment.join(&self.path())?;
             url.set_query(self.query().as_deref());
-            url
+            Ok(url)
+        }
+
+        /// The MIME type of the body, if it is not the default type of its `RequestBody` variant.
+        /// Defaults to `None`.
+        ///
+        /// The clients send this type, or the default type of the body they built.
+        fn content_type_override(&self) -> Option<Cow<'static, str>> {
+            None
         }
 
-        //noinspection RsConstantConditionIf
         /// If `body` is populated, indicates the body MIME type (defaults to JSON).
         ///
-        /// Implementors generally do not need to override this.
+        /// Implementors should override `content_type_override` instead, which the clients use.
         fn content_type(&self) -> Option<Cow<'static, str>> {
-            match Self::body(self) {
-                Some(RequestBody::Json(_)) => Some(Cow::Borrowed("application/json")),
-                Some(RequestBody::Raw(_)) => Some(Cow::Borrowed("application/octet-stream")),
-                Some(RequestBody::MultiPart(_)) => Some(Cow::Borrowed("multipart/form-data")),
-                None => None,
-            }
+            self.content_type_override()
+                .or_else(|| Some(Cow::Borrowed(Self::body(self)?.content_type())))
         }
     }
 }
@@ -108,6 +308,17 @@ impl<T: ApiResult, U: EndpointSpec> Endpoint<T> for U {}
 /// If the request succeeds, the call will resolve to a `ResultType`.
 pub trait Endpoint<ResultType: ApiResult>: EndpointSpec {}
 
+/// A list endpoint whose results are split across several pages.
+///
+/// Implementing this trait allows the clients to fetch every page of results one after the other,
+/// see `async_api::Client::paginate`.
+pub trait Paginated: EndpointSpec {
+    /// Points the endpoint at the page following the one described by `result_info`.
+    ///
+    /// Returns `false` if there are no more pages to fetch.
+    fn next_page(&mut self, result_info: &ResultInfo) -> bool;
+}
+
 /// A utility function for serializing parameters into a URL query string.
 #[inline]
 pub fn serialize_query<Q: Serialize>(q: &Q) -> Option<String> {
@@ -5,6 +5,9 @@ pub mod auth;
 pub mod client;
 pub mod endpoint;
 pub mod response;
+pub mod secret;
+
+pub use secret::Secret;
 
 use serde::{Deserialize, Serialize};
 
@@ -12,8 +15,12 @@ use serde::{Deserialize, Serialize};
 /// Errors encountered while trying to connect to the Cloudflare API
 pub enum Error {
     /// An error via the `reqwest` crate
+    #[cfg(feature = "reqwest")]
     #[error("Reqwest returned an error when connecting to the Cloudflare API: {0}")]
     ReqwestError(#[from] reqwest::Error),
+    /// The URL of the environment is invalid
+    #[error("The environment URL is invalid: {0}")]
+    InvalidUrl(#[from] url::ParseError),
 }
 
 #[derive(Serialize, Clone, Debug)]
@@ -55,21 +62,87 @@ pub enum SearchMatch {
 }
 
 /// Which environment (host path) to use for API calls
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Environment {
     /// The production endpoint: `https://api.cloudflare.com/client/v4`
     Production,
-    /// A custom endpoint (for example, a `mockito` server)
+    /// The FedRAMP endpoint of Cloudflare for Government: `https://api.fed.cloudflare.com/client/v4`
+    FedRamp,
+    /// A custom endpoint (for example, a `mockito` server, or a proxy in front of the API).
+    ///
+    /// The URL may have a path prefix, under which endpoint paths are joined. Prefer
+    /// `Environment::custom`, which checks the URL up front instead of on every request.
     Custom(String),
 }
 
-impl<'a> From<&'a Environment> for url::Url {
-    fn from(environment: &Environment) -> Self {
-        match environment {
-            Environment::Production => {
-                url::Url::parse("https://api.cloudflare.com/client/v4/").unwrap()
-            }
-            Environment::Custom(url) => url::Url::parse(url.as_str()).unwrap(),
+impl Environment {
+    /// A custom endpoint, after checking that its URL is valid, e.g.
+    /// `https://proxy.internal/cf/v4/`.
+    pub fn custom(url: impl Into<String>) -> Result<Environment, url::ParseError> {
+        let environment = Environment::Custom(url.into());
+        url::Url::try_from(&environment)?;
+        Ok(environment)
+    }
+
+    /// Joins an endpoint path under the base URL of the environment.
+    ///
+    /// Unlike `Url::join`, this keeps the path of the base URL even if the endpoint path starts
+    /// with a `/`, or the base URL doesn't end with one.
+    pub fn join(&self, path: &str) -> Result<url::Url, url::ParseError> {
+        // The `./` prefix keeps a `:` in the first segment from being read as a scheme.
+        url::Url::try_from(self)?.join(&format!("./{}", path.trim_start_matches('/')))
+    }
+}
+
+impl<'a> TryFrom<&'a Environment> for url::Url {
+    type Error = url::ParseError;
+
+    /// The base URL of the environment, whose path always ends with a `/`.
+    fn try_from(environment: &Environment) -> Result<Self, Self::Error> {
+        let url = match environment {
+            Environment::Production => "https://api.cloudflare.com/client/v4/",
+            Environment::FedRamp => "https://api.fed.cloudflare.com/client/v4/",
+            Environment::Custom(url) => url.as_str(),
+        };
+        let mut url = url::Url::parse(url)?;
+        if url.cannot_be_a_base() {
+            return Err(url::ParseError::RelativeUrlWithCannotBeABaseBase);
         }
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+        Ok(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_paths_under_the_base_url() {
+        let join = |environment: &Environment, path| environment.join(path).unwrap().to_string();
+
+        let proxy = Environment::custom("https://proxy.internal/cf/v4").unwrap();
+        assert_eq!(
+            join(&proxy, "/zones/abc"),
+            "https://proxy.internal/cf/v4/zones/abc"
+        );
+        assert_eq!(
+            join(&proxy, "accounts/a:b/storage/kv"),
+            "https://proxy.internal/cf/v4/accounts/a:b/storage/kv"
+        );
+        assert_eq!(
+            join(&Environment::Production, "/zones"),
+            "https://api.cloudflare.com/client/v4/zones"
+        );
+        assert_eq!(
+            join(&Environment::FedRamp, "zones"),
+            "https://api.fed.cloudflare.com/client/v4/zones"
+        );
+
+        assert!(Environment::custom("proxy.internal/cf/v4").is_err());
+        assert!(Environment::custom("mailto:api@example.com").is_err());
     }
 }
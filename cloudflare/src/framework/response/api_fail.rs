@@ -1,4 +1,7 @@
-use crate::framework::response::ResponseInfo;
+use crate::framework::auth::CredentialsError;
+use crate::framework::client::transport::TransportError;
+use crate::framework::endpoint::RequestError;
+use crate::framework::response::{ErrorCode, ResponseInfo};
 use serde::{Deserialize, Serialize};
 use serde_json::value::Value as JValue;
 use std::collections::HashMap;
@@ -22,21 +25,186 @@ impl PartialEq for ApiErrors {
 
 impl Eq for ApiErrors {}
 
+/// What is known about a request which the API answered with an error.
+#[derive(Clone, Debug, Default)]
+pub struct ErrorContext {
+    pub method: http::Method,
+    /// The URL of the request, with secrets in its user info and query string redacted.
+    pub url: String,
+    /// The headers of the response.
+    pub headers: http::HeaderMap,
+    /// The start of the response body, when it could not be parsed as `ApiErrors`.
+    pub raw_body: Option<String>,
+}
+
+impl ErrorContext {
+    /// The maximum length of `raw_body`, in bytes.
+    pub const MAX_RAW_BODY_LEN: usize = 1024;
+
+    /// The `cf-ray` header of the response, which identifies the request for Cloudflare support.
+    pub fn cf_ray(&self) -> Option<&str> {
+        self.headers.get("cf-ray")?.to_str().ok()
+    }
+
+    /// Keeps the start of a response body, replacing invalid UTF-8.
+    pub(crate) fn truncate_body(body: &[u8]) -> String {
+        if body.len() <= Self::MAX_RAW_BODY_LEN {
+            return String::from_utf8_lossy(body).into_owned();
+        }
+        let mut truncated = String::from_utf8_lossy(&body[..Self::MAX_RAW_BODY_LEN]).into_owned();
+        // The cut may have split a character in two.
+        if truncated.ends_with(char::REPLACEMENT_CHARACTER) {
+            truncated.pop();
+        }
+        truncated.push_str("...");
+        truncated
+    }
+}
+
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ApiFailure {
-    Error(reqwest::StatusCode, ApiErrors),
-    Invalid(reqwest::Error),
+    /// The API answered with a non-2XX status.
+    ///
+    /// The context is ignored when comparing failures.
+    Error(http::StatusCode, ApiErrors, Box<ErrorContext>),
+    /// The request could not be sent, or its response could not be received.
+    Transport(TransportError),
+    /// The API answered with a 2XX status, but its body could not be deserialized.
+    Deserialize(serde_json::Error),
+    /// The API answered with a 2XX status, but not with the media type expected by the endpoint.
+    UnexpectedContentType {
+        expected: &'static str,
+        received: Option<String>,
+    },
+    /// The HTTP request could not be built, e.g. because a header value contains invalid
+    /// characters.
+    InvalidRequest(http::Error),
+    /// The endpoint could not be built into a request, or breaks a documented limit of the API.
+    /// Nothing was sent.
+    InvalidEndpoint(RequestError),
+    /// The credentials provider of the client could not provide credentials. Nothing was sent.
+    Credentials(CredentialsError),
+    /// The request was stopped by a middleware, before it was sent.
+    Rejected(Box<dyn Error + Send + Sync>),
+    /// The request was cancelled by the `CancellationToken` of its options.
+    Cancelled,
+}
+
+impl Error for ApiFailure {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ApiFailure::Transport(error) => Some(error),
+            ApiFailure::Deserialize(error) => Some(error),
+            ApiFailure::InvalidRequest(error) => Some(error),
+            ApiFailure::InvalidEndpoint(error) => Some(error),
+            ApiFailure::Credentials(error) => Some(error),
+            ApiFailure::Rejected(error) => Some(&**error),
+            ApiFailure::Error(..)
+            | ApiFailure::UnexpectedContentType { .. }
+            | ApiFailure::Cancelled => None,
+        }
+    }
 }
 
-impl Error for ApiFailure {}
+impl ApiFailure {
+    /// The status of the response, if the API answered.
+    pub fn status(&self) -> Option<http::StatusCode> {
+        match self {
+            ApiFailure::Error(status, ..) => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// The codes of the errors returned by the API, if it answered.
+    pub fn error_codes(&self) -> impl Iterator<Item = ErrorCode> + '_ {
+        let errors = match self {
+            ApiFailure::Error(_, errors, _) => errors.errors.as_slice(),
+            _ => &[],
+        };
+        errors.iter().map(ResponseInfo::error_code)
+    }
+
+    /// The context of the request, if the API answered.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            ApiFailure::Error(_, _, context) => Some(context),
+            _ => None,
+        }
+    }
+
+    /// The `cf-ray` header of the response, if the API answered.
+    pub fn cf_ray(&self) -> Option<&str> {
+        self.context()?.cf_ray()
+    }
+
+    pub fn has_error_code(&self, code: ErrorCode) -> bool {
+        self.error_codes().any(|c| c == code)
+    }
+
+    /// Whether the resource does not exist, from the status (404) or the error codes.
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(http::StatusCode::NOT_FOUND)
+            || self.error_codes().any(ErrorCode::is_not_found)
+    }
+
+    /// Whether the request conflicts with an existing resource, from the status (409) or the
+    /// error codes.
+    pub fn is_conflict(&self) -> bool {
+        self.status() == Some(http::StatusCode::CONFLICT)
+            || self.error_codes().any(ErrorCode::is_conflict)
+    }
+
+    /// Whether the credentials were rejected, from the status (401 or 403) or the error codes.
+    pub fn is_auth(&self) -> bool {
+        matches!(
+            self.status(),
+            Some(http::StatusCode::UNAUTHORIZED | http::StatusCode::FORBIDDEN)
+        ) || self.error_codes().any(ErrorCode::is_auth)
+    }
+
+    /// Whether too many requests were sent, from the status (429) or the error codes.
+    pub fn is_rate_limited(&self) -> bool {
+        self.status() == Some(http::StatusCode::TOO_MANY_REQUESTS)
+            || self.error_codes().any(ErrorCode::is_rate_limited)
+    }
+}
 
 impl PartialEq for ApiFailure {
     fn eq(&self, other: &ApiFailure) -> bool {
         match (self, other) {
-            (ApiFailure::Invalid(e1), ApiFailure::Invalid(e2)) => e1.to_string() == e2.to_string(),
-            (ApiFailure::Error(status1, e1), ApiFailure::Error(status2, e2)) => {
+            (ApiFailure::Transport(e1), ApiFailure::Transport(e2)) => {
+                e1.to_string() == e2.to_string()
+            }
+            (ApiFailure::Error(status1, e1, _), ApiFailure::Error(status2, e2, _)) => {
                 status1 == status2 && e1 == e2
             }
+            (ApiFailure::Deserialize(e1), ApiFailure::Deserialize(e2)) => {
+                e1.to_string() == e2.to_string()
+            }
+            (
+                ApiFailure::UnexpectedContentType {
+                    expected: expected1,
+                    received: received1,
+                },
+                ApiFailure::UnexpectedContentType {
+                    expected: expected2,
+                    received: received2,
+                },
+            ) => expected1 == expected2 && received1 == received2,
+            (ApiFailure::InvalidRequest(e1), ApiFailure::InvalidRequest(e2)) => {
+                e1.to_string() == e2.to_string()
+            }
+            (ApiFailure::InvalidEndpoint(e1), ApiFailure::InvalidEndpoint(e2)) => {
+                e1.to_string() == e2.to_string()
+            }
+            (ApiFailure::Credentials(e1), ApiFailure::Credentials(e2)) => {
+                e1.to_string() == e2.to_string()
+            }
+            (ApiFailure::Rejected(e1), ApiFailure::Rejected(e2)) => {
+                e1.to_string() == e2.to_string()
+            }
+            (ApiFailure::Cancelled, ApiFailure::Cancelled) => true,
             _ => false,
         }
     }
@@ -46,37 +214,82 @@ impl Eq for ApiFailure {}
 impl fmt::Display for ApiFailure {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ApiFailure::Error(status, api_errors) => {
+            ApiFailure::Error(status, api_errors, context) => {
                 let mut output = format!("HTTP {status}");
+                if !context.url.is_empty() {
+                    let _ = write!(output, " for {} {}", context.method, context.url);
+                }
+                if let Some(ray) = context.cf_ray() {
+                    let _ = write!(output, " (cf-ray: {ray})");
+                }
                 for err in &api_errors.errors {
                     let _ = write!(output, "\n{}: {} ({:?})", err.code, err.message, err.other);
                 }
                 for (k, v) in &api_errors.other {
                     let _ = write!(output, "\n{k}: {v}");
                 }
+                if let Some(body) = &context.raw_body {
+                    let _ = write!(output, "\n{body}");
+                }
                 write!(f, "{output}")
             }
-            ApiFailure::Invalid(err) => write!(f, "{err}"),
+            ApiFailure::Transport(err) => write!(f, "{err}"),
+            ApiFailure::Deserialize(err) => write!(f, "Could not parse the API response: {err}"),
+            ApiFailure::UnexpectedContentType { expected, received } => write!(
+                f,
+                "Expected a response of type {expected}, received {}",
+                received.as_deref().unwrap_or("no type")
+            ),
+            ApiFailure::InvalidRequest(err) => write!(f, "Could not build the API request: {err}"),
+            ApiFailure::InvalidEndpoint(err) => write!(f, "Invalid API request: {err}"),
+            ApiFailure::Credentials(err) => write!(f, "Could not get the API credentials: {err}"),
+            ApiFailure::Rejected(err) => write!(f, "The request was rejected: {err}"),
+            ApiFailure::Cancelled => write!(f, "The request was cancelled"),
         }
     }
 }
 
+impl From<TransportError> for ApiFailure {
+    fn from(error: TransportError) -> Self {
+        ApiFailure::Transport(error)
+    }
+}
+
+#[cfg(feature = "reqwest")]
 impl From<reqwest::Error> for ApiFailure {
     fn from(error: reqwest::Error) -> Self {
-        ApiFailure::Invalid(error)
+        ApiFailure::Transport(error.into())
+    }
+}
+
+impl From<http::Error> for ApiFailure {
+    fn from(error: http::Error) -> Self {
+        ApiFailure::InvalidRequest(error)
+    }
+}
+
+impl From<CredentialsError> for ApiFailure {
+    fn from(error: CredentialsError) -> Self {
+        ApiFailure::Credentials(error)
+    }
+}
+
+impl From<RequestError> for ApiFailure {
+    fn from(error: RequestError) -> Self {
+        ApiFailure::InvalidEndpoint(error)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::framework::response::ResponseInfo;
+    use crate::framework::response::{ErrorCode, ResponseInfo};
     use std::collections::HashMap;
 
     #[test]
     fn api_failure_eq() {
         let err1 = ApiFailure::Error(
-            reqwest::StatusCode::NOT_FOUND,
+            http::StatusCode::NOT_FOUND,
             ApiErrors {
                 errors: vec![ResponseInfo {
                     code: 1000,
@@ -85,11 +298,12 @@ mod tests {
                 }],
                 other: HashMap::new(),
             },
+            Box::default(),
         );
         assert_eq!(err1, err1);
 
         let err2 = ApiFailure::Error(
-            reqwest::StatusCode::NOT_FOUND,
+            http::StatusCode::NOT_FOUND,
             ApiErrors {
                 errors: vec![ResponseInfo {
                     code: 1000,
@@ -98,13 +312,117 @@ mod tests {
                 }],
                 other: HashMap::new(),
             },
+            Box::default(),
         );
         assert_ne!(err2, err1);
 
-        let not_real_website = "notavalid:url.evena little";
-        let fail = ApiFailure::Invalid(reqwest::blocking::get(not_real_website).unwrap_err());
-        assert_eq!(fail, fail);
-        assert_ne!(fail, err1);
-        assert_ne!(fail, err2);
+        let mut err3 =
+            ApiFailure::Error(err1.status().unwrap(), ApiErrors::default(), Box::default());
+        if let ApiFailure::Error(_, errors, context) = &mut err3 {
+            errors.errors = vec![ResponseInfo {
+                code: 1000,
+                message: "some failed".to_owned(),
+                other: HashMap::new(),
+            }];
+            context.url = "https://api.cloudflare.com/client/v4/zones".to_owned();
+        }
+        assert_eq!(err3, err1);
+
+        #[cfg(feature = "blocking")]
+        {
+            let not_real_website = "notavalid:url.evena little";
+            let fail = ApiFailure::from(reqwest::blocking::get(not_real_website).unwrap_err());
+            assert_eq!(fail, fail);
+            assert_ne!(fail, err1);
+            assert_ne!(fail, err2);
+        }
+    }
+
+    #[test]
+    fn api_failure_source() {
+        let failure = ApiFailure::from(CredentialsError::NotFound("no token".into()));
+        let source = failure.source().unwrap();
+        assert!(source.is::<CredentialsError>());
+        assert_eq!(
+            source.to_string(),
+            CredentialsError::NotFound("no token".into()).to_string()
+        );
+
+        let failure = ApiFailure::Rejected("read-only mode".into());
+        assert_eq!(failure.source().unwrap().to_string(), "read-only mode");
+
+        assert!(ApiFailure::Cancelled.source().is_none());
+    }
+
+    #[test]
+    fn api_failure_classification() {
+        let failure = |status: u16, code: u32| {
+            ApiFailure::Error(
+                http::StatusCode::from_u16(status).unwrap(),
+                ApiErrors {
+                    errors: vec![ResponseInfo {
+                        code,
+                        message: "failed".to_owned(),
+                        other: HashMap::new(),
+                    }],
+                    other: HashMap::new(),
+                },
+                Box::default(),
+            )
+        };
+
+        let record_exists = failure(400, 81058);
+        assert!(record_exists.is_conflict());
+        assert!(!record_exists.is_not_found());
+        assert!(record_exists.has_error_code(ErrorCode::IdenticalDnsRecordExists));
+
+        assert!(failure(404, 10009).is_not_found());
+        assert!(failure(400, 81044).is_not_found());
+        assert!(failure(409, 1).is_conflict());
+        assert!(failure(400, 9109).is_auth());
+        assert!(failure(403, 1).is_auth());
+        assert!(failure(429, 1).is_rate_limited());
+        assert!(failure(400, 971).is_rate_limited());
+
+        let other = failure(400, 1);
+        assert_eq!(
+            other.error_codes().collect::<Vec<_>>(),
+            [ErrorCode::from(1)]
+        );
+        assert!(!other.is_not_found() && !other.is_conflict());
+        assert!(!other.is_auth() && !other.is_rate_limited());
+    }
+
+    #[test]
+    fn api_failure_display_context() {
+        let mut headers = http::HeaderMap::new();
+        headers.insert("cf-ray", "8c1c7bbd7d3f3b1a-CDG".parse().unwrap());
+        let failure = ApiFailure::Error(
+            http::StatusCode::BAD_GATEWAY,
+            ApiErrors::default(),
+            Box::new(ErrorContext {
+                method: http::Method::DELETE,
+                url: "https://api.cloudflare.com/client/v4/zones/abc".to_owned(),
+                headers,
+                raw_body: Some("<html>Bad gateway</html>".to_owned()),
+            }),
+        );
+        assert_eq!(failure.cf_ray(), Some("8c1c7bbd7d3f3b1a-CDG"));
+        assert_eq!(
+            failure.to_string(),
+            "HTTP 502 Bad Gateway for DELETE https://api.cloudflare.com/client/v4/zones/abc \
+             (cf-ray: 8c1c7bbd7d3f3b1a-CDG)\n<html>Bad gateway</html>"
+        );
+    }
+
+    #[test]
+    fn truncates_raw_body() {
+        assert_eq!(ErrorContext::truncate_body(b"short"), "short");
+
+        let mut body = vec![b'a'; ErrorContext::MAX_RAW_BODY_LEN - 1];
+        body.extend_from_slice("é and more".as_bytes());
+        let truncated = ErrorContext::truncate_body(&body);
+        assert_eq!(truncated.len(), ErrorContext::MAX_RAW_BODY_LEN - 1 + 3);
+        assert!(truncated.ends_with("aaa..."));
     }
 }
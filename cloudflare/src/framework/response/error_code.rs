@@ -0,0 +1,229 @@
+use std::fmt;
+
+/// The code of an error returned by the API, in `ResponseInfo::code`.
+///
+/// Only the codes callers usually branch on, and which mean the same for every API, are named.
+/// Any other code is kept as `Other`, so converting a code and back never loses it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    // Authentication
+    /// 6003: Invalid request headers.
+    InvalidRequestHeaders,
+    /// 6103: Invalid format for the X-Auth-Key header.
+    InvalidAuthKeyFormat,
+    /// 6111: Invalid format for the Authorization header.
+    InvalidAuthorizationHeader,
+    /// 9103: Unknown X-Auth-Key or X-Auth-Email.
+    UnknownAuthKey,
+    /// 9106: Missing X-Auth-Key, X-Auth-Email or Authorization headers.
+    MissingCredentials,
+    /// 9109: Unauthorized to access the requested resource.
+    Unauthorized,
+    /// 10000: Authentication error.
+    AuthenticationError,
+
+    // Routing and rate limiting
+    /// 971: Too many requests, requests should be throttled.
+    RateLimited,
+    /// 7000: No route for that URI.
+    NoRoute,
+    /// 7001: Method not allowed for this endpoint.
+    MethodNotAllowed,
+    /// 7003: Could not route to the URI, the object identifier is probably invalid.
+    InvalidObjectIdentifier,
+
+    // Zones
+    /// 1049: The domain is not a registered domain.
+    InvalidDomain,
+    /// 1061: A zone with this name already exists.
+    ZoneAlreadyExists,
+
+    // DNS
+    /// 9004: This record type cannot be proxied.
+    DnsRecordNotProxiable,
+    /// 9005: The content of the record is invalid for its type.
+    InvalidDnsContent,
+    /// 81044: The record does not exist.
+    DnsRecordNotFound,
+    /// 81053: An A, AAAA or CNAME record with that host already exists.
+    DnsHostConflict,
+    /// 81057: The record already exists.
+    DnsRecordAlreadyExists,
+    /// 81058: An identical record already exists.
+    IdenticalDnsRecordExists,
+
+    // Workers and Workers KV
+    /// 10021: The Worker script failed validation.
+    InvalidWorkerScript,
+    /// 10009: The key does not exist in the namespace.
+    KvKeyNotFound,
+    /// 10013: The namespace does not exist.
+    KvNamespaceNotFound,
+    /// 10014: A namespace with this title already exists.
+    KvNamespaceAlreadyExists,
+
+    // Cloudflare Tunnel
+    /// 1003: The tunnel does not exist.
+    TunnelNotFound,
+    /// 1013: A tunnel with this name already exists.
+    TunnelAlreadyExists,
+
+    // Custom hostnames
+    /// 1406: A custom hostname with this name already exists.
+    CustomHostnameAlreadyExists,
+
+    /// Any other code.
+    Other(OtherCode),
+}
+
+/// A code which `ErrorCode` does not name, see `ErrorCode::code`.
+///
+/// It can only be made by converting a code to an `ErrorCode`, so that a named code always
+/// compares equal to its variant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct OtherCode(u32);
+
+impl ErrorCode {
+    /// The numeric code, as returned by the API.
+    pub fn code(self) -> u32 {
+        match self {
+            ErrorCode::InvalidRequestHeaders => 6003,
+            ErrorCode::InvalidAuthKeyFormat => 6103,
+            ErrorCode::InvalidAuthorizationHeader => 6111,
+            ErrorCode::UnknownAuthKey => 9103,
+            ErrorCode::MissingCredentials => 9106,
+            ErrorCode::Unauthorized => 9109,
+            ErrorCode::AuthenticationError => 10000,
+            ErrorCode::RateLimited => 971,
+            ErrorCode::NoRoute => 7000,
+            ErrorCode::MethodNotAllowed => 7001,
+            ErrorCode::InvalidObjectIdentifier => 7003,
+            ErrorCode::InvalidDomain => 1049,
+            ErrorCode::ZoneAlreadyExists => 1061,
+            ErrorCode::DnsRecordNotProxiable => 9004,
+            ErrorCode::InvalidDnsContent => 9005,
+            ErrorCode::DnsRecordNotFound => 81044,
+            ErrorCode::DnsHostConflict => 81053,
+            ErrorCode::DnsRecordAlreadyExists => 81057,
+            ErrorCode::IdenticalDnsRecordExists => 81058,
+            ErrorCode::InvalidWorkerScript => 10021,
+            ErrorCode::KvKeyNotFound => 10009,
+            ErrorCode::KvNamespaceNotFound => 10013,
+            ErrorCode::KvNamespaceAlreadyExists => 10014,
+            ErrorCode::TunnelNotFound => 1003,
+            ErrorCode::TunnelAlreadyExists => 1013,
+            ErrorCode::CustomHostnameAlreadyExists => 1406,
+            ErrorCode::Other(code) => code.0,
+        }
+    }
+
+    /// Whether the resource (or the route to it) does not exist.
+    pub fn is_not_found(self) -> bool {
+        matches!(
+            self,
+            ErrorCode::NoRoute
+                | ErrorCode::InvalidObjectIdentifier
+                | ErrorCode::DnsRecordNotFound
+                | ErrorCode::KvKeyNotFound
+                | ErrorCode::KvNamespaceNotFound
+                | ErrorCode::TunnelNotFound
+        )
+    }
+
+    /// Whether the request conflicts with an existing resource.
+    pub fn is_conflict(self) -> bool {
+        matches!(
+            self,
+            ErrorCode::ZoneAlreadyExists
+                | ErrorCode::DnsHostConflict
+                | ErrorCode::DnsRecordAlreadyExists
+                | ErrorCode::IdenticalDnsRecordExists
+                | ErrorCode::KvNamespaceAlreadyExists
+                | ErrorCode::TunnelAlreadyExists
+                | ErrorCode::CustomHostnameAlreadyExists
+        )
+    }
+
+    /// Whether the credentials are missing, invalid, or not allowed to make the request.
+    pub fn is_auth(self) -> bool {
+        matches!(
+            self,
+            ErrorCode::InvalidRequestHeaders
+                | ErrorCode::InvalidAuthKeyFormat
+                | ErrorCode::InvalidAuthorizationHeader
+                | ErrorCode::UnknownAuthKey
+                | ErrorCode::MissingCredentials
+                | ErrorCode::Unauthorized
+                | ErrorCode::AuthenticationError
+        )
+    }
+
+    pub fn is_rate_limited(self) -> bool {
+        self == ErrorCode::RateLimited
+    }
+}
+
+impl From<u32> for ErrorCode {
+    fn from(code: u32) -> Self {
+        match code {
+            6003 => ErrorCode::InvalidRequestHeaders,
+            6103 => ErrorCode::InvalidAuthKeyFormat,
+            6111 => ErrorCode::InvalidAuthorizationHeader,
+            9103 => ErrorCode::UnknownAuthKey,
+            9106 => ErrorCode::MissingCredentials,
+            9109 => ErrorCode::Unauthorized,
+            10000 => ErrorCode::AuthenticationError,
+            971 => ErrorCode::RateLimited,
+            7000 => ErrorCode::NoRoute,
+            7001 => ErrorCode::MethodNotAllowed,
+            7003 => ErrorCode::InvalidObjectIdentifier,
+            1049 => ErrorCode::InvalidDomain,
+            1061 => ErrorCode::ZoneAlreadyExists,
+            9004 => ErrorCode::DnsRecordNotProxiable,
+            9005 => ErrorCode::InvalidDnsContent,
+            81044 => ErrorCode::DnsRecordNotFound,
+            81053 => ErrorCode::DnsHostConflict,
+            81057 => ErrorCode::DnsRecordAlreadyExists,
+            81058 => ErrorCode::IdenticalDnsRecordExists,
+            10021 => ErrorCode::InvalidWorkerScript,
+            10009 => ErrorCode::KvKeyNotFound,
+            10013 => ErrorCode::KvNamespaceNotFound,
+            10014 => ErrorCode::KvNamespaceAlreadyExists,
+            1003 => ErrorCode::TunnelNotFound,
+            1013 => ErrorCode::TunnelAlreadyExists,
+            1406 => ErrorCode::CustomHostnameAlreadyExists,
+            code => ErrorCode::Other(OtherCode(code)),
+        }
+    }
+}
+
+impl From<ErrorCode> for u32 {
+    fn from(code: ErrorCode) -> Self {
+        code.code()
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_code_round_trip() {
+        for code in 0..100_000 {
+            assert_eq!(ErrorCode::from(code).code(), code);
+        }
+        assert_eq!(ErrorCode::from(81044), ErrorCode::DnsRecordNotFound);
+        assert_eq!(ErrorCode::from(12345), ErrorCode::Other(OtherCode(12345)));
+        // The meaning of these codes depends on the API.
+        for code in 10004..=10008 {
+            assert!(matches!(ErrorCode::from(code), ErrorCode::Other(_)));
+        }
+    }
+}
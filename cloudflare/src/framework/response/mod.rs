@@ -1,9 +1,10 @@
 mod api_fail;
+mod error_code;
 
 pub use api_fail::*;
+pub use error_code::{ErrorCode, OtherCode};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use serde_json::value::Value as JsonValue;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
@@ -12,15 +13,82 @@ use std::fmt::Debug;
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
 pub struct ApiSuccess<ResultType> {
     pub result: ResultType,
-    pub result_info: Option<JsonValue>,
+    pub result_info: Option<ResultInfo>,
     #[serde(default)]
     pub messages: Vec<ResponseInfo>,
     #[serde(default)]
     pub errors: Vec<ResponseInfo>,
 }
 
+/// Pagination information returned alongside the results of list endpoints.
+///
+/// Page-based endpoints fill in the page counters, while cursor-based endpoints (e.g. Workers KV
+/// key listing) return a `cursor` to pass to the next request instead. Any other field is kept in
+/// `other`.
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ResultInfo {
+    /// Current page within the paginated list of results.
+    pub page: Option<u32>,
+    /// Number of results per page.
+    pub per_page: Option<u32>,
+    /// Number of results on the current page.
+    pub count: Option<u32>,
+    /// Total number of results across all pages.
+    pub total_count: Option<u32>,
+    /// Total number of pages.
+    pub total_pages: Option<u32>,
+    /// Opaque token to request the next set of results. Empty once the listing is exhausted.
+    pub cursor: Option<String>,
+    /// The fields not listed above, e.g. the `cursors` object of some list endpoints.
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+impl ResultInfo {
+    /// Returns the number of the page after this one, or `None` if this is the last page.
+    pub fn next_page(&self) -> Option<u32> {
+        let page = self.page?;
+        let is_last = match (self.total_pages, self.count, self.per_page) {
+            (Some(total_pages), _, _) => page >= total_pages,
+            // Not every endpoint reports the number of pages. A partial page is the last one.
+            (None, Some(count), Some(per_page)) => count < per_page,
+            _ => true,
+        };
+        (!is_last).then_some(page + 1)
+    }
+
+    /// Returns the cursor for the next set of results, or `None` if there are no more results.
+    pub fn next_cursor(&self) -> Option<&str> {
+        self.cursor.as_deref().filter(|cursor| !cursor.is_empty())
+    }
+}
+
 pub type ApiResponse<ResultType> = Result<ResultType, ApiFailure>;
 
+/// A decoded response, with the status and headers it was received with.
+///
+/// Some endpoints return information in headers only, e.g. the expiration of Workers KV values,
+/// or the `cf-ray` identifying a request.
+#[derive(Debug)]
+pub struct Response<ResultType> {
+    pub status: http::StatusCode,
+    pub headers: http::HeaderMap,
+    pub result: ResultType,
+}
+
+impl<ResultType> Response<ResultType> {
+    /// The `cf-ray` header of the response, which identifies the request for Cloudflare support.
+    pub fn cf_ray(&self) -> Option<&str> {
+        self.headers.get("cf-ray")?.to_str().ok()
+    }
+
+    pub fn into_result(self) -> ResultType {
+        self.result
+    }
+}
+
 pub trait ApiResult: DeserializeOwned + Debug {}
 
 impl<T> ApiResult for ApiSuccess<T> where T: ApiResult {}
@@ -29,27 +97,53 @@ impl<T> ApiResult for ApiSuccess<T> where T: ApiResult {}
 impl ApiResult for () {}
 impl ApiResult for serde_json::Value {}
 
-/// A helper trait to avoid trait bounds issues in the clients.
-pub trait ResponseConverter<JsonResponse>: Sized {
-    fn from_raw(bytes: Vec<u8>) -> Self;
-    fn from_json(api: ApiSuccess<JsonResponse>) -> Self;
+/// The kind of body an endpoint responds with, which decides how the body of a successful
+/// response is turned into its result:
+///
+/// - `ApiSuccess<T>` for JSON responses, which are parsed.
+/// - `Vec<u8>` for raw bytes, which are returned as they are.
+/// - `()` for empty responses, whose body is ignored.
+///
+/// It bounds `EndpointSpec::ResponseType`, so an endpoint cannot expect a kind of response which
+/// does not match its result type, nor a JSON result other than its `JsonResponse`.
+pub trait ResponseKind: Sized {
+    /// The result parsed from JSON responses: `T` for `ApiSuccess<T>`, and `()` for the other
+    /// kinds.
+    type Json: ApiResult;
+
+    /// The media type the API responds with. Responses of another type are returned as
+    /// `ApiFailure::UnexpectedContentType`. Defaults to `None`, which accepts any type.
+    const CONTENT_TYPE: Option<&'static str> = None;
+
+    /// Turns the body of a successful response into the result of the endpoint.
+    fn from_body(body: Vec<u8>) -> serde_json::Result<Self>;
 }
+
 // JSON endpoints
-impl<T> ResponseConverter<T> for ApiSuccess<T> {
-    fn from_raw(_bytes: Vec<u8>) -> Self {
-        panic!("This endpoint does not return raw bytes")
-    }
-    fn from_json(api: ApiSuccess<T>) -> Self {
-        api
+impl<T: ApiResult> ResponseKind for ApiSuccess<T> {
+    type Json = T;
+
+    fn from_body(body: Vec<u8>) -> serde_json::Result<Self> {
+        serde_json::from_slice(&body)
     }
 }
+
 // Raw endpoints
-impl ResponseConverter<()> for Vec<u8> {
-    fn from_raw(bytes: Vec<u8>) -> Self {
-        bytes
+impl ResponseKind for Vec<u8> {
+    type Json = ();
+    const CONTENT_TYPE: Option<&'static str> = Some("application/octet-stream");
+
+    fn from_body(body: Vec<u8>) -> serde_json::Result<Self> {
+        Ok(body)
     }
-    fn from_json(_api: ApiSuccess<()>) -> Self {
-        panic!("This endpoint does not return JSON")
+}
+
+// Empty endpoints
+impl ResponseKind for () {
+    type Json = ();
+
+    fn from_body(_body: Vec<u8>) -> serde_json::Result<Self> {
+        Ok(())
     }
 }
 
@@ -57,12 +151,20 @@ impl ResponseConverter<()> for Vec<u8> {
 /// It does NOT compare the `other` values.
 #[derive(Deserialize, Serialize, Debug)]
 pub struct ResponseInfo {
-    pub code: u16,
+    /// The error code, see `error_code`. It is a `u32` as some codes, such as 81044, do not fit in
+    /// a `u16`.
+    pub code: u32,
     pub message: String,
     #[serde(flatten)]
     pub other: HashMap<String, serde_json::Value>,
 }
 
+impl ResponseInfo {
+    pub fn error_code(&self) -> ErrorCode {
+        ErrorCode::from(self.code)
+    }
+}
+
 impl PartialEq for ResponseInfo {
     fn eq(&self, other: &Self) -> bool {
         self.code == other.code && self.message == other.message
@@ -78,3 +180,57 @@ impl fmt::Display for ResponseInfo {
         write!(f, "Error {}: {}", self.code, self.message)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn result_info_next_page() {
+        let info: ResultInfo = serde_json::from_value(serde_json::json!({
+            "page": 1, "per_page": 20, "count": 20, "total_count": 45, "total_pages": 3
+        }))
+        .unwrap();
+        assert_eq!(info.next_page(), Some(2));
+
+        let last = ResultInfo {
+            page: Some(3),
+            count: Some(5),
+            ..info.clone()
+        };
+        assert_eq!(last.next_page(), None);
+
+        let without_totals = ResultInfo {
+            total_count: None,
+            total_pages: None,
+            ..info
+        };
+        assert_eq!(without_totals.next_page(), Some(2));
+        let partial = ResultInfo {
+            count: Some(3),
+            ..without_totals
+        };
+        assert_eq!(partial.next_page(), None);
+    }
+
+    #[test]
+    fn result_info_next_cursor() {
+        let info: ResultInfo =
+            serde_json::from_value(serde_json::json!({"count": 1, "cursor": "abc"})).unwrap();
+        assert_eq!(info.next_cursor(), Some("abc"));
+
+        let exhausted: ResultInfo =
+            serde_json::from_value(serde_json::json!({"count": 0, "cursor": ""})).unwrap();
+        assert_eq!(exhausted.next_cursor(), None);
+
+        let info: ResultInfo = serde_json::from_value(serde_json::json!({
+            "count": 1, "cursors": {"after": "def"}
+        }))
+        .unwrap();
+        assert_eq!(info.other["cursors"], serde_json::json!({"after": "def"}));
+        assert_eq!(
+            serde_json::to_value(&info).unwrap(),
+            serde_json::json!({"count": 1, "cursors": {"after": "def"}})
+        );
+    }
+}
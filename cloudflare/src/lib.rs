@@ -2,3 +2,5 @@
 
 pub mod endpoints;
 pub mod framework;
+#[cfg(feature = "testing")]
+pub mod testing;
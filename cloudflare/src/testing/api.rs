@@ -0,0 +1,264 @@
+//! Routing, response envelopes and helpers shared by the faked resources.
+use super::{dns, kv, r2, tunnels, zones};
+use chrono::{SecondsFormat, Utc};
+use http::header::{AUTHORIZATION, CONTENT_TYPE};
+use http::{Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+pub(super) type Request = http::Request<Vec<u8>>;
+pub(super) type Response = http::Response<Vec<u8>>;
+pub(super) type Result<T> = std::result::Result<T, ApiError>;
+
+/// The whole state of the fake API.
+#[derive(Default)]
+pub(super) struct State {
+    pub(super) zones: zones::Zones,
+    pub(super) dns: dns::DnsRecords,
+    pub(super) kv: kv::Namespaces,
+    pub(super) r2: r2::Buckets,
+    pub(super) tunnels: tunnels::Tunnels,
+}
+
+/// A successful result, before it is wrapped in the response envelope.
+pub(super) enum Reply {
+    Json(Value),
+    /// A page of results, along with its `result_info`.
+    Page(Vec<Value>, Value),
+    /// A raw body, sent without envelope.
+    Raw(Vec<u8>),
+}
+
+/// An error, sent in the `errors` of the response envelope.
+#[derive(Debug)]
+pub(super) struct ApiError {
+    status: StatusCode,
+    code: u32,
+    message: String,
+}
+
+impl ApiError {
+    pub(super) fn new(status: StatusCode, code: u32, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub(super) fn bad_request(message: impl Into<String>) -> Self {
+        ApiError::new(StatusCode::BAD_REQUEST, 1004, message)
+    }
+
+    fn no_route() -> Self {
+        ApiError::new(StatusCode::NOT_FOUND, 7000, "No route for that URI")
+    }
+
+    fn method_not_allowed() -> Self {
+        ApiError::new(
+            StatusCode::METHOD_NOT_ALLOWED,
+            7001,
+            "Method not allowed for this endpoint",
+        )
+    }
+}
+
+impl State {
+    pub(super) fn handle(&mut self, request: &Request) -> Response {
+        match self.route(request) {
+            Ok(Reply::Raw(body)) => http::Response::builder()
+                .header(CONTENT_TYPE, "application/octet-stream")
+                .body(body),
+            Ok(Reply::Json(result)) => envelope(StatusCode::OK, json!({ "result": result })),
+            Ok(Reply::Page(result, result_info)) => envelope(
+                StatusCode::OK,
+                json!({ "result": result, "result_info": result_info }),
+            ),
+            Err(error) => envelope(
+                error.status,
+                json!({
+                    "success": false,
+                    "result": null,
+                    "errors": [{ "code": error.code, "message": error.message }],
+                }),
+            ),
+        }
+        .expect("Responses are valid")
+    }
+
+    fn route(&mut self, request: &Request) -> Result<Reply> {
+        authenticate(request)?;
+
+        let path = request.uri().path();
+        let path = path.strip_prefix("/client/v4").unwrap_or(path);
+        let segments: Vec<String> = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| match urlencoding::decode(segment) {
+                Ok(segment) => segment.into_owned(),
+                Err(_) => segment.to_owned(),
+            })
+            .collect();
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+        let call = Call {
+            method: request.method(),
+            query: Query::parse(request.uri().query()),
+            request,
+        };
+
+        match segments.as_slice() {
+            ["zones"] | ["zones", _] | ["zones", _, "activation_check"] => {
+                self.zones.handle(&call, &segments[1..])
+            }
+            ["zones", zone, "dns_records", path @ ..] => {
+                let zone = self.zones.get(zone)?.clone();
+                self.dns.handle(&call, &zone, path)
+            }
+            ["accounts", account, "storage", "kv", "namespaces", path @ ..] => {
+                self.kv.handle(&call, account, path)
+            }
+            ["accounts", account, "r2", "buckets", path @ ..] => {
+                self.r2.handle(&call, account, path)
+            }
+            ["accounts", account, "cfd_tunnel", path @ ..] => {
+                self.tunnels.handle(&call, account, path)
+            }
+            _ => Err(ApiError::no_route()),
+        }
+    }
+}
+
+/// Builds a JSON response with the common fields of the envelope, unless `body` sets them.
+fn envelope(status: StatusCode, mut body: Value) -> http::Result<Response> {
+    let fields = body.as_object_mut().expect("Envelopes are objects");
+    for (key, value) in [
+        ("success", json!(true)),
+        ("errors", json!([])),
+        ("messages", json!([])),
+    ] {
+        fields.entry(key).or_insert(value);
+    }
+    http::Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(body.to_string().into_bytes())
+}
+
+/// Like the real API, only checks that some credentials are present.
+fn authenticate(request: &Request) -> Result<()> {
+    let headers = request.headers();
+    let authenticated = headers.contains_key(AUTHORIZATION)
+        || headers.contains_key("x-auth-user-service-key")
+        || (headers.contains_key("x-auth-email") && headers.contains_key("x-auth-key"));
+    if authenticated {
+        Ok(())
+    } else {
+        Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            9106,
+            "Missing X-Auth-Email header",
+        ))
+    }
+}
+
+/// An incoming API call, as seen by the handler of a resource.
+pub(super) struct Call<'a> {
+    method: &'a Method,
+    pub(super) query: Query,
+    pub(super) request: &'a Request,
+}
+
+impl Call<'_> {
+    pub(super) fn method(&self) -> &str {
+        self.method.as_str()
+    }
+
+    pub(super) fn method_not_allowed(&self) -> Result<Reply> {
+        Err(ApiError::method_not_allowed())
+    }
+
+    pub(super) fn no_route(&self) -> Result<Reply> {
+        Err(ApiError::no_route())
+    }
+
+    /// Deserializes the JSON body of the request.
+    pub(super) fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_slice(self.request.body()).map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                9207,
+                format!("Request body is invalid: {e}"),
+            )
+        })
+    }
+
+    pub(super) fn body(&self) -> &[u8] {
+        self.request.body()
+    }
+
+    pub(super) fn content_type(&self) -> &str {
+        self.request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+    }
+}
+
+/// The query string of a request.
+pub(super) struct Query(HashMap<String, String>);
+
+impl Query {
+    fn parse(query: Option<&str>) -> Query {
+        Query(serde_urlencoded::from_str(query.unwrap_or_default()).unwrap_or_default())
+    }
+
+    pub(super) fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    pub(super) fn parse_value<T: FromStr>(&self, name: &str) -> Result<Option<T>> {
+        self.get(name)
+            .map(|value| {
+                value.parse().map_err(|_| {
+                    ApiError::bad_request(format!("Invalid value for {name}: {value}"))
+                })
+            })
+            .transpose()
+    }
+
+    /// Returns the requested page of `items`, from the `page` and `per_page` parameters.
+    pub(super) fn paginate(&self, items: Vec<Value>, default_per_page: usize) -> Result<Reply> {
+        let page = self.parse_value::<usize>("page")?.unwrap_or(1).max(1);
+        let per_page = self
+            .parse_value::<usize>("per_page")?
+            .unwrap_or(default_per_page)
+            .max(1);
+        let total_count = items.len();
+        let result: Vec<Value> = items
+            .into_iter()
+            .skip((page - 1) * per_page)
+            .take(per_page)
+            .collect();
+        let result_info = json!({
+            "page": page,
+            "per_page": per_page,
+            "count": result.len(),
+            "total_count": total_count,
+            "total_pages": total_count.div_ceil(per_page),
+        });
+        Ok(Reply::Page(result, result_info))
+    }
+}
+
+/// A random identifier, formatted like the ones of zones, records and namespaces.
+pub(super) fn new_id() -> String {
+    format!("{:032x}", fastrand::u128(..))
+}
+
+/// The current time, formatted like in API responses.
+pub(super) fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true)
+}
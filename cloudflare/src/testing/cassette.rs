@@ -0,0 +1,517 @@
+//! Recording of API interactions to cassette files, and their replay.
+use crate::framework::client::codec::{redact_url, CREDENTIAL_HEADERS, REDACTED};
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+use crate::framework::client::transport::BlockingTransport;
+use crate::framework::client::transport::{Transport, TransportError, TransportFuture};
+use bytes::Bytes;
+use http::header::{CONTENT_TYPE, SET_COOKIE};
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Replaces the random boundary of multipart bodies, so that recorded requests can be matched.
+const BOUNDARY: &str = "BOUNDARY";
+
+/// A list of recorded request/response pairs, stored as a JSON file.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// A request, without its credentials.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// The URL of the request, with secrets in its user info and query string redacted.
+    pub url: String,
+    /// The headers of the request, with credentials redacted.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: Option<Body>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RecordedResponse {
+    pub status: u16,
+    /// The headers of the response, except cookies.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: Option<Body>,
+}
+
+/// A request or response body. JSON bodies are kept as JSON, to keep cassettes readable.
+#[serde_with::serde_as]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Body {
+    Json(Value),
+    Text(String),
+    Base64(#[serde_as(as = "serde_with::base64::Base64")] Vec<u8>),
+}
+
+impl Cassette {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Cassette> {
+        let file = std::fs::read(path)?;
+        serde_json::from_slice(&file).map_err(io::Error::other)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut json = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        json.push(b'\n');
+        std::fs::write(path, json)
+    }
+}
+
+impl RecordedRequest {
+    fn new(request: &http::Request<Bytes>) -> RecordedRequest {
+        let mut headers = record_headers(request.headers());
+        let mut body = request.body().to_vec();
+        let boundary = content_type(request.headers())
+            .split(';')
+            .find_map(|param| param.trim().strip_prefix("boundary="))
+            .map(|boundary| boundary.trim_matches('"').to_owned());
+        if let Some(boundary) = boundary {
+            body = replace(&body, boundary.as_bytes(), BOUNDARY.as_bytes());
+            if let Some(content_type) = headers.get_mut(CONTENT_TYPE.as_str()) {
+                *content_type = content_type.replace(&boundary, BOUNDARY);
+            }
+        }
+        RecordedRequest {
+            method: request.method().to_string(),
+            url: redact_url(&request.uri().to_string()),
+            headers,
+            body: Body::new(body, content_type(request.headers())),
+        }
+    }
+
+    /// Whether `self`, a request being sent, matches a recorded request.
+    fn matches(&self, recorded: &RecordedRequest) -> bool {
+        self.method == recorded.method && self.url == recorded.url && self.body == recorded.body
+    }
+}
+
+impl RecordedResponse {
+    fn new(response: &http::Response<Bytes>) -> RecordedResponse {
+        let mut headers = record_headers(response.headers());
+        headers.remove(SET_COOKIE.as_str());
+        RecordedResponse {
+            status: response.status().as_u16(),
+            headers,
+            body: Body::new(response.body().to_vec(), content_type(response.headers())),
+        }
+    }
+
+    fn to_response(&self) -> Result<http::Response<Bytes>, TransportError> {
+        let body = self.body.as_ref().map(Body::to_bytes).unwrap_or_default();
+        let mut response = http::Response::new(Bytes::from(body));
+        *response.status_mut() = StatusCode::from_u16(self.status).map_err(invalid_cassette)?;
+        for (name, value) in &self.headers {
+            response.headers_mut().insert(
+                HeaderName::try_from(name).map_err(invalid_cassette)?,
+                HeaderValue::try_from(value).map_err(invalid_cassette)?,
+            );
+        }
+        Ok(response)
+    }
+}
+
+impl Body {
+    fn new(bytes: Vec<u8>, content_type: &str) -> Option<Body> {
+        if bytes.is_empty() {
+            return None;
+        }
+        if content_type.starts_with("application/json") {
+            if let Ok(json) = serde_json::from_slice(&bytes) {
+                return Some(Body::Json(json));
+            }
+        }
+        Some(match String::from_utf8(bytes) {
+            Ok(text) => Body::Text(text),
+            Err(error) => Body::Base64(error.into_bytes()),
+        })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Body::Json(json) => json.to_string().into_bytes(),
+            Body::Text(text) => text.clone().into_bytes(),
+            Body::Base64(bytes) => bytes.clone(),
+        }
+    }
+}
+
+/// Records the requests sent through another transport, along with their responses.
+///
+/// Transport errors are passed on, but not recorded. The recording shares its state with its
+/// clones, so a clone can be kept to save the cassette once the client is done:
+///
+/// ```no_run
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// use cloudflare::endpoints::zones::zone::ListZones;
+/// use cloudflare::framework::auth::Credentials;
+/// use cloudflare::framework::client::async_api::Client;
+/// use cloudflare::framework::client::transport::ReqwestTransport;
+/// use cloudflare::framework::client::ClientConfig;
+/// use cloudflare::framework::Environment;
+/// use cloudflare::testing::RecordingTransport;
+///
+/// let recorder = RecordingTransport::new(ReqwestTransport::default());
+/// let credentials = Credentials::UserAuthToken { token: "secret".into() };
+/// let client = Client::new_with_transport(
+///     recorder.clone(),
+///     credentials,
+///     ClientConfig::default(),
+///     Environment::Production,
+/// );
+/// client.request(&ListZones { params: Default::default() }).await?;
+/// recorder.save("tests/cassettes/list_zones.json")?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct RecordingTransport<T> {
+    inner: Arc<T>,
+    interactions: Arc<Mutex<Vec<Interaction>>>,
+}
+
+impl<T> RecordingTransport<T> {
+    pub fn new(inner: T) -> Self {
+        RecordingTransport {
+            inner: Arc::new(inner),
+            interactions: Default::default(),
+        }
+    }
+
+    /// Returns the interactions recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        Cassette {
+            interactions: self.interactions.lock().unwrap().clone(),
+        }
+    }
+
+    /// Writes the interactions recorded so far to a cassette file.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.cassette().save(path)
+    }
+
+    fn record(&self, request: RecordedRequest, response: &http::Response<Bytes>) {
+        let interaction = Interaction {
+            request,
+            response: RecordedResponse::new(response),
+        };
+        self.interactions.lock().unwrap().push(interaction);
+    }
+}
+
+impl<T> Clone for RecordingTransport<T> {
+    fn clone(&self) -> Self {
+        RecordingTransport {
+            inner: self.inner.clone(),
+            interactions: self.interactions.clone(),
+        }
+    }
+}
+
+impl<T: Transport> Transport for RecordingTransport<T> {
+    fn send(&self, request: http::Request<Bytes>) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let recorded = RecordedRequest::new(&request);
+            let response = self.inner.send(request).await?;
+            self.record(recorded, &response);
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+impl<T: BlockingTransport> BlockingTransport for RecordingTransport<T> {
+    fn send(&self, request: http::Request<Bytes>) -> Result<http::Response<Bytes>, TransportError> {
+        let recorded = RecordedRequest::new(&request);
+        let response = self.inner.send(request)?;
+        self.record(recorded, &response);
+        Ok(response)
+    }
+}
+
+/// Answers requests with the responses of a cassette, without sending anything.
+///
+/// A request is answered by the first interaction not played yet with the same method, URL and
+/// body. Headers are not compared, so credentials do not have to match the recorded ones.
+///
+/// # Panics
+///
+/// Sending a request which matches no remaining interaction panics, so that tests cannot silently
+/// depend on requests which were not recorded.
+pub struct ReplayTransport {
+    interactions: Arc<Mutex<Vec<(Interaction, bool)>>>,
+}
+
+impl ReplayTransport {
+    pub fn new(cassette: Cassette) -> Self {
+        let interactions = cassette
+            .interactions
+            .into_iter()
+            .map(|interaction| (interaction, false))
+            .collect();
+        ReplayTransport {
+            interactions: Arc::new(Mutex::new(interactions)),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(ReplayTransport::new(Cassette::load(path)?))
+    }
+
+    /// Panics if some interactions of the cassette have not been played.
+    pub fn assert_finished(&self) {
+        let interactions = self.interactions.lock().unwrap();
+        let remaining: Vec<_> = interactions
+            .iter()
+            .filter(|(_, played)| !played)
+            .map(|(interaction, _)| {
+                format!("{} {}", interaction.request.method, interaction.request.url)
+            })
+            .collect();
+        assert!(
+            remaining.is_empty(),
+            "Recorded requests were not sent: {}",
+            remaining.join(", ")
+        );
+    }
+
+    fn replay(
+        &self,
+        request: &http::Request<Bytes>,
+    ) -> Result<http::Response<Bytes>, TransportError> {
+        let request = RecordedRequest::new(request);
+        let mut interactions = self.interactions.lock().unwrap();
+        let Some((interaction, played)) = interactions
+            .iter_mut()
+            .find(|(interaction, played)| !played && request.matches(&interaction.request))
+        else {
+            // Release the lock first, so that the transport can still be used after the panic.
+            drop(interactions);
+            panic!(
+                "No recorded interaction matches {} {} with body {:?}",
+                request.method, request.url, request.body
+            );
+        };
+        *played = true;
+        interaction.response.to_response()
+    }
+}
+
+impl Clone for ReplayTransport {
+    fn clone(&self) -> Self {
+        ReplayTransport {
+            interactions: self.interactions.clone(),
+        }
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(&self, request: http::Request<Bytes>) -> TransportFuture<'_> {
+        let response = self.replay(&request);
+        Box::pin(async move { response })
+    }
+}
+
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+impl BlockingTransport for ReplayTransport {
+    fn send(&self, request: http::Request<Bytes>) -> Result<http::Response<Bytes>, TransportError> {
+        self.replay(&request)
+    }
+}
+
+fn invalid_cassette(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> TransportError {
+    TransportError::new(
+        crate::framework::client::transport::TransportErrorKind::Other,
+        error,
+    )
+}
+
+fn content_type(headers: &HeaderMap) -> &str {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+}
+
+fn record_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if value.is_sensitive() || CREDENTIAL_HEADERS.contains(&name.as_str()) {
+                REDACTED.to_owned()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+fn replace(haystack: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+    let mut replaced = Vec::with_capacity(haystack.len());
+    let mut rest = haystack;
+    while let Some(index) = rest.windows(from.len()).position(|window| window == from) {
+        replaced.extend_from_slice(&rest[..index]);
+        replaced.extend_from_slice(to);
+        rest = &rest[index + from.len()..];
+    }
+    replaced.extend_from_slice(rest);
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoints::dns::dns::{CreateDnsRecord, DnsContent, DnsRecordOperator};
+    use crate::endpoints::workerskv::write_key::{
+        WriteKey, WriteKeyBody, WriteKeyBodyMetadata, WriteKeyParams,
+    };
+    use crate::endpoints::zones::zone::{
+        AccountParams, CreateZone, CreateZoneParams, ListZones, ListZonesParams,
+    };
+    use crate::framework::auth::Credentials;
+    use crate::framework::client::async_api::Client;
+    use crate::framework::client::transport::ReqwestTransport;
+    use crate::framework::client::ClientConfig;
+    use crate::framework::Environment;
+    use crate::testing::FakeServer;
+    use std::net::Ipv4Addr;
+
+    fn client(transport: impl Transport + 'static, environment: Environment) -> Client {
+        let credentials = Credentials::UserAuthToken {
+            token: "secret-token".into(),
+        };
+        Client::new_with_transport(transport, credentials, ClientConfig::default(), environment)
+    }
+
+    async fn create_zone_and_record(client: &Client) -> (String, String) {
+        let zone = client
+            .request(&CreateZone {
+                params: CreateZoneParams {
+                    name: "example.com",
+                    account: AccountParams {
+                        id: Some("account"),
+                    },
+                    zone_type: None,
+                },
+            })
+            .await
+            .unwrap()
+            .result
+            .unwrap();
+        let record = client
+            .request(&CreateDnsRecord {
+                zone_identifier: &zone.id,
+                params: DnsRecordOperator {
+                    ttl: None,
+                    proxied: None,
+                    name: "www".into(),
+                    content: DnsContent::A {
+                        content: Ipv4Addr::new(192, 0, 2, 1),
+                    },
+                },
+            })
+            .await
+            .unwrap();
+        let zones = client
+            .request(&ListZones {
+                params: ListZonesParams::default(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(zones.result[0].id, zone.id);
+        (zone.id, record.result.id)
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let server = FakeServer::start().unwrap();
+        let environment = server.environment();
+        let recorder = RecordingTransport::new(ReqwestTransport::default());
+        let recorded = create_zone_and_record(&client(recorder.clone(), environment.clone())).await;
+        drop(server);
+
+        let path = std::env::temp_dir().join(format!("cassette-{:x}.json", fastrand::u64(..)));
+        recorder.save(&path).unwrap();
+        let file = std::fs::read_to_string(&path).unwrap();
+        assert!(!file.contains("secret-token"));
+        assert!(file.contains(REDACTED));
+
+        let cassette = Cassette::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(cassette.interactions.len(), 3);
+        assert_eq!(cassette.interactions[1].request.method, "POST");
+        assert_eq!(cassette.interactions[1].response.status, 200);
+
+        // The server is gone: responses can only come from the cassette.
+        let replay = ReplayTransport::new(cassette);
+        let replayed = create_zone_and_record(&client(replay.clone(), environment)).await;
+        assert_eq!(recorded, replayed);
+        replay.assert_finished();
+    }
+
+    #[tokio::test]
+    async fn test_record_multipart() {
+        let server = FakeServer::start().unwrap();
+        let recorder = RecordingTransport::new(ReqwestTransport::default());
+        let recording = client(recorder.clone(), server.environment());
+        let write = WriteKey {
+            account_identifier: "account",
+            namespace_identifier: "namespace",
+            key: "key",
+            params: WriteKeyParams::default(),
+            body: WriteKeyBody::Metadata(WriteKeyBodyMetadata {
+                value: b"value".to_vec(),
+                metadata: serde_json::json!({ "a": 1 }),
+            }),
+        };
+        // The namespace does not exist, but the request is recorded all the same.
+        recording.request(&write).await.unwrap_err();
+
+        let cassette = recorder.cassette();
+        let request = &cassette.interactions[0].request;
+        assert_eq!(
+            request.headers["content-type"],
+            "multipart/form-data; boundary=BOUNDARY"
+        );
+        let Some(Body::Text(body)) = &request.body else {
+            panic!("Expected a text body, got {:?}", request.body);
+        };
+        assert!(body.starts_with("--BOUNDARY\r\n"));
+        assert_eq!(request.headers["authorization"], REDACTED);
+        assert_eq!(cassette.interactions[0].response.status, 404);
+
+        // Boundaries are random, yet the request matches its recording.
+        let replay = ReplayTransport::new(cassette);
+        let replaying = client(replay.clone(), server.environment());
+        replaying.request(&write).await.unwrap_err();
+        replay.assert_finished();
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "No recorded interaction matches GET")]
+    async fn test_replay_unmatched_request() {
+        let client = client(
+            ReplayTransport::new(Cassette::default()),
+            Environment::Production,
+        );
+        let _ = client
+            .request(&ListZones {
+                params: ListZonesParams::default(),
+            })
+            .await;
+    }
+}
@@ -0,0 +1,318 @@
+use super::api::{new_id, now, ApiError, Call, Reply, Result};
+use super::zones::Zone;
+use http::StatusCode;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// DNS records, by zone identifier.
+#[derive(Default)]
+pub(super) struct DnsRecords(HashMap<String, Vec<Record>>);
+
+#[derive(Clone)]
+struct Record {
+    id: String,
+    name: String,
+    record_type: String,
+    content: String,
+    priority: Option<u16>,
+    ttl: u32,
+    proxied: bool,
+    created_on: String,
+    modified_on: String,
+}
+
+/// The fields of a record, as sent to create, overwrite or patch it.
+#[derive(Deserialize, Default)]
+struct Params {
+    id: Option<String>,
+    name: Option<String>,
+    #[serde(rename = "type")]
+    record_type: Option<String>,
+    content: Option<String>,
+    priority: Option<u16>,
+    ttl: Option<u32>,
+    proxied: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct Batch {
+    #[serde(default)]
+    deletes: Vec<Params>,
+    #[serde(default)]
+    patches: Vec<Params>,
+    #[serde(default)]
+    puts: Vec<Params>,
+    #[serde(default)]
+    posts: Vec<Params>,
+}
+
+impl DnsRecords {
+    pub(super) fn handle(&mut self, call: &Call, zone: &Zone, path: &[&str]) -> Result<Reply> {
+        let records = self.0.entry(zone.id.clone()).or_default();
+        match (call.method(), path) {
+            ("GET", []) => list(records, zone, call),
+            ("POST", []) => {
+                let record = create(records, zone, call.json()?)?;
+                Ok(Reply::Json(record.to_json(zone)))
+            }
+            ("POST", ["batch"]) => {
+                let mut updated = records.clone();
+                let result = batch(&mut updated, zone, call.json()?)?;
+                // Batches are atomic: records are only changed if every operation succeeded.
+                *records = updated;
+                Ok(Reply::Json(result))
+            }
+            ("GET", [id]) => Ok(Reply::Json(find(records, id)?.to_json(zone))),
+            ("PUT", [id]) => {
+                let record = overwrite(records, zone, id, call.json()?)?;
+                Ok(Reply::Json(record.to_json(zone)))
+            }
+            ("PATCH", [id]) => {
+                let record = patch(records, zone, id, call.json()?)?;
+                Ok(Reply::Json(record.to_json(zone)))
+            }
+            ("DELETE", [id]) => {
+                delete(records, id)?;
+                Ok(Reply::Json(json!({ "id": id })))
+            }
+            (_, [] | ["batch"] | [_]) => call.method_not_allowed(),
+            _ => call.no_route(),
+        }
+    }
+}
+
+fn list(records: &[Record], zone: &Zone, call: &Call) -> Result<Reply> {
+    let query = &call.query;
+    let records = records
+        .iter()
+        .filter(|record| query.get("type").is_none_or(|t| record.record_type == t))
+        .filter(|record| query.get("name").is_none_or(|name| record.name == name))
+        .filter(|record| query.get("content").is_none_or(|c| record.content == c))
+        .map(|record| record.to_json(zone))
+        .collect();
+    query.paginate(records, 100)
+}
+
+fn create<'a>(records: &'a mut Vec<Record>, zone: &Zone, params: Params) -> Result<&'a Record> {
+    let (Some(name), Some(record_type), Some(content)) =
+        (params.name, params.record_type, params.content)
+    else {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            9000,
+            "DNS records require a name, type and content.",
+        ));
+    };
+    let now = now();
+    let record = Record {
+        id: new_id(),
+        name: full_name(&name, zone),
+        record_type,
+        content,
+        priority: params.priority,
+        ttl: params.ttl.unwrap_or(1),
+        proxied: params.proxied.unwrap_or(false),
+        created_on: now.clone(),
+        modified_on: now,
+    };
+    validate(records, &record)?;
+    records.push(record);
+    Ok(records.last().expect("The record was just added"))
+}
+
+fn overwrite<'a>(
+    records: &'a mut [Record],
+    zone: &Zone,
+    id: &str,
+    params: Params,
+) -> Result<&'a Record> {
+    let (Some(name), Some(record_type), Some(content)) =
+        (params.name, params.record_type, params.content)
+    else {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            9000,
+            "DNS records require a name, type and content.",
+        ));
+    };
+    let existing = find(records, id)?;
+    let record = Record {
+        name: full_name(&name, zone),
+        record_type,
+        content,
+        priority: params.priority,
+        ttl: params.ttl.unwrap_or(1),
+        proxied: params.proxied.unwrap_or(false),
+        modified_on: now(),
+        ..existing.clone()
+    };
+    replace(records, record)
+}
+
+fn patch<'a>(
+    records: &'a mut [Record],
+    zone: &Zone,
+    id: &str,
+    params: Params,
+) -> Result<&'a Record> {
+    let existing = find(records, id)?.clone();
+    let record = Record {
+        name: params
+            .name
+            .map_or(existing.name, |name| full_name(&name, zone)),
+        record_type: params.record_type.unwrap_or(existing.record_type),
+        content: params.content.unwrap_or(existing.content),
+        priority: params.priority.or(existing.priority),
+        ttl: params.ttl.unwrap_or(existing.ttl),
+        proxied: params.proxied.unwrap_or(existing.proxied),
+        modified_on: now(),
+        ..existing
+    };
+    replace(records, record)
+}
+
+fn delete(records: &mut Vec<Record>, id: &str) -> Result<Record> {
+    let index = records
+        .iter()
+        .position(|record| record.id == id)
+        .ok_or_else(record_not_found)?;
+    Ok(records.remove(index))
+}
+
+/// Applies the operations of a batch in the same order as the API: deletes, patches, puts, then
+/// posts.
+fn batch(records: &mut Vec<Record>, zone: &Zone, batch: Batch) -> Result<Value> {
+    let mut deletes = Vec::new();
+    for params in batch.deletes {
+        let record = delete(records, params.id.as_deref().unwrap_or_default())?;
+        deletes.push(record.to_json(zone));
+    }
+    let mut patches = Vec::new();
+    for mut params in batch.patches {
+        let id = params.id.take().unwrap_or_default();
+        patches.push(patch(records, zone, &id, params)?.to_json(zone));
+    }
+    let mut puts = Vec::new();
+    for mut params in batch.puts {
+        let id = params.id.take().unwrap_or_default();
+        puts.push(overwrite(records, zone, &id, params)?.to_json(zone));
+    }
+    let mut posts = Vec::new();
+    for params in batch.posts {
+        posts.push(create(records, zone, params)?.to_json(zone));
+    }
+    Ok(json!({
+        "deletes": deletes,
+        "patches": patches,
+        "puts": puts,
+        "posts": posts,
+    }))
+}
+
+fn find<'a>(records: &'a [Record], id: &str) -> Result<&'a Record> {
+    records
+        .iter()
+        .find(|record| record.id == id)
+        .ok_or_else(record_not_found)
+}
+
+fn replace(records: &mut [Record], record: Record) -> Result<&Record> {
+    let others: Vec<Record> = records
+        .iter()
+        .filter(|other| other.id != record.id)
+        .cloned()
+        .collect();
+    validate(&others, &record)?;
+    let slot = records
+        .iter_mut()
+        .find(|other| other.id == record.id)
+        .ok_or_else(record_not_found)?;
+    *slot = record;
+    Ok(slot)
+}
+
+fn record_not_found() -> ApiError {
+    ApiError::new(StatusCode::NOT_FOUND, 81044, "Record does not exist.")
+}
+
+/// Checks the content of a record, and that it does not conflict with the other records.
+fn validate(others: &[Record], record: &Record) -> Result<()> {
+    let valid_content = match record.record_type.as_str() {
+        "A" => record.content.parse::<Ipv4Addr>().is_ok(),
+        "AAAA" => record.content.parse::<Ipv6Addr>().is_ok(),
+        _ => !record.content.is_empty(),
+    };
+    if !valid_content {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            9005,
+            format!("Content for {} record is invalid.", record.record_type),
+        ));
+    }
+    if record.proxied && !matches!(record.record_type.as_str(), "A" | "AAAA" | "CNAME") {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            9004,
+            "This record type cannot be proxied.",
+        ));
+    }
+
+    for other in others.iter().filter(|other| other.name == record.name) {
+        if other.record_type == record.record_type && other.content == record.content {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                81058,
+                "An identical record already exists.",
+            ));
+        }
+        let is_address = |t: &str| matches!(t, "A" | "AAAA" | "CNAME");
+        if (record.record_type == "CNAME" || other.record_type == "CNAME")
+            && is_address(&record.record_type)
+            && is_address(&other.record_type)
+        {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                81053,
+                "An A, AAAA, or CNAME record with that host already exists.",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Turns a record name relative to the zone (e.g. `www` or `@`) into a fully qualified one.
+fn full_name(name: &str, zone: &Zone) -> String {
+    let name = name.trim_end_matches('.').to_lowercase();
+    if name.is_empty() || name == "@" {
+        zone.name.clone()
+    } else if name == zone.name || name.ends_with(&format!(".{}", zone.name)) {
+        name
+    } else {
+        format!("{name}.{}", zone.name)
+    }
+}
+
+impl Record {
+    fn to_json(&self, zone: &Zone) -> Value {
+        let mut json = json!({
+            "id": self.id,
+            "zone_id": zone.id,
+            "zone_name": zone.name,
+            "name": self.name,
+            "type": self.record_type,
+            "content": self.content,
+            "ttl": self.ttl,
+            "proxied": self.proxied,
+            "proxiable": matches!(self.record_type.as_str(), "A" | "AAAA" | "CNAME"),
+            "meta": {},
+            "created_on": self.created_on,
+            "modified_on": self.modified_on,
+        });
+        if let Some(priority) = self.priority {
+            json["priority"] = priority.into();
+        }
+        json
+    }
+}
@@ -0,0 +1,343 @@
+use super::api::{new_id, ApiError, Call, Reply, Result};
+use chrono::Utc;
+use http::StatusCode;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/// The maximum length of a key name, in bytes.
+const MAX_KEY_LENGTH: usize = 512;
+
+/// Workers KV namespaces of every account.
+#[derive(Default)]
+pub(super) struct Namespaces(Vec<Namespace>);
+
+struct Namespace {
+    account: String,
+    id: String,
+    title: String,
+    keys: BTreeMap<String, Entry>,
+}
+
+struct Entry {
+    value: Vec<u8>,
+    metadata: Option<Value>,
+    /// Seconds since the UNIX epoch.
+    expiration: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct Title {
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct BulkWrite {
+    key: String,
+    value: String,
+    expiration: Option<i64>,
+    expiration_ttl: Option<i64>,
+    #[serde(default)]
+    base64: bool,
+    metadata: Option<Value>,
+}
+
+#[serde_with::serde_as]
+#[derive(Deserialize)]
+struct Base64(#[serde_as(as = "serde_with::base64::Base64")] Vec<u8>);
+
+impl Namespaces {
+    pub(super) fn handle(&mut self, call: &Call, account: &str, path: &[&str]) -> Result<Reply> {
+        match (call.method(), path) {
+            ("GET", []) => {
+                let namespaces = self
+                    .0
+                    .iter()
+                    .filter(|namespace| namespace.account == account)
+                    .map(Namespace::to_json)
+                    .collect();
+                call.query.paginate(namespaces, 20)
+            }
+            ("POST", []) => {
+                let Title { title } = call.json()?;
+                self.check_title(account, &title)?;
+                let namespace = Namespace {
+                    account: account.to_owned(),
+                    id: new_id(),
+                    title,
+                    keys: BTreeMap::new(),
+                };
+                let json = namespace.to_json();
+                self.0.push(namespace);
+                Ok(Reply::Json(json))
+            }
+            ("GET", [id]) => Ok(Reply::Json(self.get(account, id)?.to_json())),
+            ("PUT", [id]) => {
+                let Title { title } = call.json()?;
+                self.get(account, id)?;
+                self.check_title(account, &title)?;
+                self.get(account, id)?.title = title;
+                Ok(Reply::Json(Value::Null))
+            }
+            ("DELETE", [id]) => {
+                self.get(account, id)?;
+                self.0
+                    .retain(|namespace| namespace.account != account || namespace.id != *id);
+                Ok(Reply::Json(Value::Null))
+            }
+            (_, [id, ..]) => self.get(account, id)?.handle(call, &path[1..]),
+            _ => call.method_not_allowed(),
+        }
+    }
+
+    fn get(&mut self, account: &str, id: &str) -> Result<&mut Namespace> {
+        self.0
+            .iter_mut()
+            .find(|namespace| namespace.account == account && namespace.id == id)
+            .ok_or_else(|| {
+                ApiError::new(
+                    StatusCode::NOT_FOUND,
+                    10013,
+                    "get namespace: 'namespace not found'",
+                )
+            })
+    }
+
+    fn check_title(&self, account: &str, title: &str) -> Result<()> {
+        let exists = self
+            .0
+            .iter()
+            .any(|namespace| namespace.account == account && namespace.title == title);
+        if exists {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                10014,
+                "create namespace: 'a namespace with this account ID and title already exists'",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Namespace {
+    fn handle(&mut self, call: &Call, path: &[&str]) -> Result<Reply> {
+        self.remove_expired();
+        match (call.method(), path) {
+            ("GET", ["keys"]) => self.list_keys(call),
+            ("GET", ["values", key]) => Ok(Reply::Raw(self.entry(key)?.value.clone())),
+            ("GET", ["metadata", key]) => Ok(Reply::Json(self.entry(key)?.metadata.clone().into())),
+            ("PUT", ["values", key]) => {
+                let (value, metadata) = if call.content_type().starts_with("multipart/form-data") {
+                    let mut fields = multipart_fields(call.content_type(), call.body())
+                        .ok_or_else(|| ApiError::bad_request("Invalid multipart body"))?;
+                    let metadata = match fields.remove("metadata") {
+                        Some(metadata) => {
+                            Some(serde_json::from_slice(&metadata).map_err(|_| {
+                                ApiError::bad_request("metadata must be valid JSON")
+                            })?)
+                        }
+                        None => None,
+                    };
+                    (fields.remove("value").unwrap_or_default(), metadata)
+                } else {
+                    (call.body().to_vec(), None)
+                };
+                let expiration = expiration(
+                    call.query.parse_value("expiration")?,
+                    call.query.parse_value("expiration_ttl")?,
+                )?;
+                self.write(key, value, metadata, expiration)?;
+                Ok(Reply::Json(Value::Null))
+            }
+            ("DELETE", ["values", key]) => {
+                self.keys.remove(*key);
+                Ok(Reply::Json(Value::Null))
+            }
+            ("PUT", ["bulk"]) => {
+                let pairs: Vec<BulkWrite> = call.json()?;
+                let count = pairs.len();
+                for pair in pairs {
+                    let value = if pair.base64 {
+                        serde_json::from_value::<Base64>(Value::String(pair.value))
+                            .map_err(|_| ApiError::bad_request("value is not valid base64"))?
+                            .0
+                    } else {
+                        pair.value.into_bytes()
+                    };
+                    let expiration = expiration(pair.expiration, pair.expiration_ttl)?;
+                    self.write(&pair.key, value, pair.metadata, expiration)?;
+                }
+                Ok(Reply::Json(bulk_result(count)))
+            }
+            ("DELETE", ["bulk"]) => {
+                let keys: Vec<String> = call.json()?;
+                for key in &keys {
+                    self.keys.remove(key);
+                }
+                Ok(Reply::Json(bulk_result(keys.len())))
+            }
+            (_, ["keys"] | ["values", _] | ["metadata", _] | ["bulk"]) => call.method_not_allowed(),
+            _ => call.no_route(),
+        }
+    }
+
+    /// Lists keys in order, using the index of the next key as cursor.
+    fn list_keys(&self, call: &Call) -> Result<Reply> {
+        let prefix = call.query.get("prefix").unwrap_or_default();
+        let limit = call.query.parse_value::<usize>("limit")?.unwrap_or(1000);
+        let start = match call.query.get("cursor") {
+            None | Some("") => 0,
+            Some(cursor) => cursor
+                .parse()
+                .map_err(|_| ApiError::bad_request("Invalid cursor"))?,
+        };
+        let keys: Vec<_> = self
+            .keys
+            .iter()
+            .filter(|(name, _)| name.starts_with(prefix))
+            .collect();
+        let page: Vec<Value> = keys
+            .iter()
+            .skip(start)
+            .take(limit)
+            .map(|(name, entry)| {
+                let mut key = json!({ "name": name });
+                if let Some(expiration) = entry.expiration {
+                    key["expiration"] = expiration.into();
+                }
+                if let Some(metadata) = &entry.metadata {
+                    key["metadata"] = metadata.clone();
+                }
+                key
+            })
+            .collect();
+        let end = start + page.len();
+        let cursor = if end < keys.len() {
+            end.to_string()
+        } else {
+            String::new()
+        };
+        let result_info = json!({ "count": page.len(), "cursor": cursor });
+        Ok(Reply::Page(page, result_info))
+    }
+
+    fn entry(&self, key: &str) -> Result<&Entry> {
+        self.keys
+            .get(key)
+            .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, 10009, "get: 'key not found'"))
+    }
+
+    fn write(
+        &mut self,
+        key: &str,
+        value: Vec<u8>,
+        metadata: Option<Value>,
+        expiration: Option<i64>,
+    ) -> Result<()> {
+        if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+            return Err(ApiError::new(
+                StatusCode::URI_TOO_LONG,
+                10033,
+                format!("Key names must be between 1 and {MAX_KEY_LENGTH} bytes long"),
+            ));
+        }
+        self.keys.insert(
+            key.to_owned(),
+            Entry {
+                value,
+                metadata,
+                expiration,
+            },
+        );
+        Ok(())
+    }
+
+    fn remove_expired(&mut self) {
+        let now = Utc::now().timestamp();
+        self.keys
+            .retain(|_, entry| entry.expiration.is_none_or(|expiration| expiration > now));
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "title": self.title,
+            "supports_url_encoding": true,
+        })
+    }
+}
+
+/// Resolves the expiration of a key, which is relative to now if `expiration_ttl` is given.
+fn expiration(expiration: Option<i64>, expiration_ttl: Option<i64>) -> Result<Option<i64>> {
+    let now = Utc::now().timestamp();
+    match (expiration, expiration_ttl) {
+        (_, Some(ttl)) if ttl < 60 => Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            10019,
+            format!("Invalid expiration_ttl of {ttl}. Expiration TTL must be at least 60."),
+        )),
+        (_, Some(ttl)) => Ok(Some(now + ttl)),
+        (Some(expiration), None) if expiration < now + 60 => Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            10018,
+            format!(
+                "Invalid expiration of {expiration}. Expiration times must be at least 60 seconds in the future."
+            ),
+        )),
+        (expiration, None) => Ok(expiration),
+    }
+}
+
+fn bulk_result(count: usize) -> Value {
+    json!({ "successful_key_count": count, "unsuccessful_keys": [] })
+}
+
+/// Parses the fields of a `multipart/form-data` body, by name.
+fn multipart_fields(content_type: &str, body: &[u8]) -> Option<HashMap<String, Vec<u8>>> {
+    let boundary = content_type
+        .split(';')
+        .find_map(|param| param.trim().strip_prefix("boundary="))?
+        .trim_matches('"');
+    let delimiter = format!("\r\n--{boundary}");
+
+    // Prepend a line break, so that the first delimiter looks like the following ones.
+    let body = [b"\r\n".as_slice(), body].concat();
+    let mut fields = HashMap::new();
+    for part in split(&body, delimiter.as_bytes()).into_iter().skip(1) {
+        if part.starts_with(b"--") {
+            break;
+        }
+        let part = part.strip_prefix(b"\r\n")?;
+        let headers_end = find(part, b"\r\n\r\n")?;
+        let headers = std::str::from_utf8(&part[..headers_end]).ok()?;
+        let name = headers.lines().find_map(|header| {
+            let (name, value) = header.split_once(':')?;
+            if !name.trim().eq_ignore_ascii_case("content-disposition") {
+                return None;
+            }
+            let name = value
+                .split(';')
+                .find_map(|p| p.trim().strip_prefix("name="))?;
+            Some(name.trim_matches('"').to_owned())
+        })?;
+        fields.insert(name, part[headers_end + 4..].to_vec());
+    }
+    Some(fields)
+}
+
+fn split<'a>(haystack: &'a [u8], needle: &[u8]) -> Vec<&'a [u8]> {
+    let mut parts = Vec::new();
+    let mut rest = haystack;
+    while let Some(index) = find(rest, needle) {
+        parts.push(&rest[..index]);
+        rest = &rest[index + needle.len()..];
+    }
+    parts.push(rest);
+    parts
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
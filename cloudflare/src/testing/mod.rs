@@ -0,0 +1,463 @@
+/*!
+An in-memory fake of the Cloudflare API, to test code using this crate without touching the
+network. Requires the `testing` feature.
+
+[`FakeServer`] runs a local HTTP server which keeps zones, DNS records, Workers KV namespaces
+and keys, R2 buckets and Cloudflare Tunnels in memory. Responses use the same envelopes, status
+codes and error codes as the real API, so resources can be created, listed and deleted like they
+would be in production:
+
+```no_run
+# async fn run() -> Result<(), Box<dyn std::error::Error>> {
+use cloudflare::endpoints::r2::r2::{CreateBucket, ListBuckets};
+use cloudflare::framework::auth::Credentials;
+use cloudflare::framework::client::async_api::Client;
+use cloudflare::framework::client::ClientConfig;
+use cloudflare::testing::FakeServer;
+
+let server = FakeServer::start()?;
+let credentials = Credentials::UserAuthToken { token: "test".into() };
+let client = Client::new(credentials, ClientConfig::default(), server.environment())?;
+
+client
+    .request(&CreateBucket { account_identifier: "account", bucket_name: "bucket" })
+    .await?;
+let buckets = client.request(&ListBuckets { account_identifier: "account" }).await?;
+assert_eq!(buckets.result.buckets[0].name, "bucket");
+# Ok(())
+# }
+```
+
+Any account identifier is accepted, and every account starts out empty. Requests must carry
+credentials, but their value is not checked.
+
+# Cassettes
+
+Exchanges with the real API can also be captured once and replayed offline.
+[`RecordingTransport`] wraps the transport of a client and records every request and its response
+into a [`Cassette`], which is saved as a JSON file. Credentials are redacted from the recording.
+[`ReplayTransport`] then answers the same requests from the cassette, and panics on any request
+which was not recorded.
+ */
+mod api;
+mod cassette;
+mod dns;
+mod kv;
+mod r2;
+mod server;
+mod tunnels;
+mod zones;
+
+pub use cassette::{
+    Body, Cassette, Interaction, RecordedRequest, RecordedResponse, RecordingTransport,
+    ReplayTransport,
+};
+
+use crate::framework::Environment;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// A local HTTP server faking the Cloudflare API. It is stopped when dropped.
+pub struct FakeServer {
+    address: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FakeServer {
+    /// Starts a server with an empty state, listening on a random local port.
+    pub fn start() -> std::io::Result<FakeServer> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        let address = listener.local_addr()?;
+        let stopped = Arc::new(AtomicBool::new(false));
+        let state = Arc::new(Mutex::new(api::State::default()));
+
+        let thread = std::thread::spawn({
+            let stopped = stopped.clone();
+            move || server::serve(listener, state, &stopped)
+        });
+
+        Ok(FakeServer {
+            address,
+            stopped,
+            thread: Some(thread),
+        })
+    }
+
+    /// The base URL of the API, e.g. `http://127.0.0.1:12345/client/v4/`.
+    pub fn url(&self) -> String {
+        format!("http://{}/client/v4/", self.address)
+    }
+
+    /// The environment to pass to a client, to send its requests to this server.
+    pub fn environment(&self) -> Environment {
+        Environment::Custom(self.url())
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // Wake the server up, so that it notices it has been stopped.
+        let _ = TcpStream::connect(self.address);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoints::cfd_tunnel::{
+        create_tunnel::{self, CreateTunnel},
+        delete_tunnel::{self, DeleteTunnel},
+        list_tunnels::{self, ListTunnels},
+        ConfigurationSrc,
+    };
+    use crate::endpoints::dns::dns::{
+        BatchDelete, BatchDnsRecords, BatchDnsRecordsParams, CreateDnsRecord, DeleteDnsRecord,
+        DnsContent, DnsRecordOperator, ListDnsRecords, ListDnsRecordsParams,
+    };
+    use crate::endpoints::r2::r2::{CreateBucket, DeleteBucket, ListBuckets};
+    use crate::endpoints::workerskv::{
+        create_namespace::{CreateNamespace, CreateNamespaceParams},
+        list_namespace_keys::{ListNamespaceKeys, ListNamespaceKeysParams},
+        read_key::ReadKey,
+        read_key_metadata::ReadKeyMetadata,
+        write_bulk::{KeyValuePair, WriteBulk},
+        write_key::{WriteKey, WriteKeyBody, WriteKeyBodyMetadata, WriteKeyParams},
+    };
+    use crate::endpoints::zones::zone::{
+        AccountParams, CreateZone, CreateZoneParams, ListZones, ListZonesParams,
+    };
+    use crate::framework::auth::Credentials;
+    use crate::framework::client::async_api::Client;
+    use crate::framework::client::ClientConfig;
+    use crate::framework::response::ApiFailure;
+    use crate::framework::Secret;
+    use futures_util::TryStreamExt;
+    use serde_json::json;
+
+    const ACCOUNT: &str = "01a7362d577a6c3019a474fd6f485823";
+
+    fn client(server: &FakeServer) -> Client {
+        let credentials = Credentials::UserAuthToken {
+            token: "test".into(),
+        };
+        Client::new(credentials, ClientConfig::default(), server.environment()).unwrap()
+    }
+
+    fn error_code(failure: ApiFailure) -> (u16, u32) {
+        match failure {
+            ApiFailure::Error(status, errors, _) => (status.as_u16(), errors.errors[0].code),
+            failure => panic!("Expected an API error, got {failure}"),
+        }
+    }
+
+    async fn create_zone(client: &Client, name: &str) -> String {
+        let zone = client
+            .request(&CreateZone {
+                params: CreateZoneParams {
+                    name,
+                    account: AccountParams { id: Some(ACCOUNT) },
+                    zone_type: None,
+                },
+            })
+            .await
+            .unwrap();
+        zone.result.unwrap().id
+    }
+
+    #[tokio::test]
+    async fn zones() {
+        let server = FakeServer::start().unwrap();
+        let client = client(&server);
+
+        for name in ["a.com", "b.com", "c.com"] {
+            create_zone(&client, name).await;
+        }
+        let zones: Vec<_> = client
+            .paginate(ListZones {
+                params: ListZonesParams {
+                    per_page: Some(2),
+                    ..Default::default()
+                },
+            })
+            .try_collect()
+            .await
+            .unwrap();
+        let names: Vec<_> = zones.iter().map(|zone| zone.name.as_str()).collect();
+        assert_eq!(names, ["a.com", "b.com", "c.com"]);
+
+        let duplicate = client
+            .request(&CreateZone {
+                params: CreateZoneParams {
+                    name: "a.com",
+                    account: AccountParams { id: Some(ACCOUNT) },
+                    zone_type: None,
+                },
+            })
+            .await;
+        assert_eq!(error_code(duplicate.unwrap_err()), (400, 1061));
+    }
+
+    #[tokio::test]
+    async fn dns_records() {
+        let server = FakeServer::start().unwrap();
+        let client = client(&server);
+        let zone_identifier = create_zone(&client, "example.com").await;
+
+        let www = client
+            .request(&CreateDnsRecord {
+                zone_identifier: &zone_identifier,
+                params: DnsRecordOperator {
+                    ttl: None,
+                    proxied: Some(true),
+                    name: "www".into(),
+                    content: DnsContent::A {
+                        content: "192.0.2.1".parse().unwrap(),
+                    },
+                },
+            })
+            .await
+            .unwrap()
+            .result;
+        assert_eq!(www.name, "www.example.com");
+        assert!(www.proxied);
+
+        let batch = client
+            .request(&BatchDnsRecords {
+                zone_identifier: &zone_identifier,
+                params: BatchDnsRecordsParams {
+                    deletes: Some(vec![BatchDelete { id: www.id.clone() }]),
+                    posts: Some(vec![DnsRecordOperator {
+                        ttl: Some(300),
+                        proxied: None,
+                        name: "example.com".into(),
+                        content: DnsContent::TXT {
+                            content: "hello".into(),
+                        },
+                    }]),
+                    ..Default::default()
+                },
+            })
+            .await
+            .unwrap()
+            .result;
+        assert_eq!(batch.deletes.unwrap()[0].id, www.id);
+        assert_eq!(batch.posts.unwrap()[0].ttl, 300);
+
+        let records = client
+            .request(&ListDnsRecords {
+                zone_identifier: &zone_identifier,
+                params: ListDnsRecordsParams::default(),
+            })
+            .await
+            .unwrap()
+            .result;
+        assert_eq!(records.len(), 1);
+        assert!(matches!(&records[0].content, DnsContent::TXT { content } if content == "hello"));
+
+        let missing = client
+            .request(&DeleteDnsRecord {
+                zone_identifier: &zone_identifier,
+                identifier: &www.id,
+            })
+            .await;
+        assert_eq!(error_code(missing.unwrap_err()), (404, 81044));
+    }
+
+    #[tokio::test]
+    async fn workers_kv() {
+        let server = FakeServer::start().unwrap();
+        let client = client(&server);
+
+        let namespace = client
+            .request(&CreateNamespace {
+                account_identifier: ACCOUNT,
+                params: CreateNamespaceParams {
+                    title: "cache".into(),
+                },
+            })
+            .await
+            .unwrap()
+            .result;
+
+        client
+            .request(&WriteKey {
+                account_identifier: ACCOUNT,
+                namespace_identifier: &namespace.id,
+                key: "user:1",
+                params: WriteKeyParams::default(),
+                body: WriteKeyBody::Metadata(WriteKeyBodyMetadata {
+                    value: b"alice".to_vec(),
+                    metadata: json!({"admin": true}),
+                }),
+            })
+            .await
+            .unwrap();
+        let bulk = (2..=4)
+            .map(|i| KeyValuePair {
+                key: format!("user:{i}"),
+                value: format!("user {i}"),
+                expiration: None,
+                expiration_ttl: None,
+                base64: None,
+            })
+            .collect();
+        client
+            .request(&WriteBulk {
+                account_identifier: ACCOUNT,
+                namespace_identifier: &namespace.id,
+                bulk_key_value_pairs: bulk,
+            })
+            .await
+            .unwrap();
+
+        let value = client
+            .request(&ReadKey {
+                account_identifier: ACCOUNT,
+                namespace_identifier: &namespace.id,
+                key: "user:1",
+            })
+            .await
+            .unwrap();
+        assert_eq!(value, b"alice");
+        let metadata = client
+            .request(&ReadKeyMetadata {
+                account_identifier: ACCOUNT,
+                namespace_identifier: &namespace.id,
+                key: "user:1",
+            })
+            .await
+            .unwrap()
+            .result;
+        assert_eq!(metadata, Some(json!({"admin": true})));
+
+        let keys: Vec<_> = client
+            .paginate(ListNamespaceKeys {
+                account_identifier: ACCOUNT,
+                namespace_identifier: &namespace.id,
+                params: ListNamespaceKeysParams {
+                    limit: Some(10),
+                    prefix: Some("user:".into()),
+                    ..Default::default()
+                },
+            })
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(keys.len(), 4);
+
+        let missing = client
+            .request(&ReadKey {
+                account_identifier: ACCOUNT,
+                namespace_identifier: &namespace.id,
+                key: "user:5",
+            })
+            .await;
+        assert_eq!(error_code(missing.unwrap_err()), (404, 10009));
+    }
+
+    #[tokio::test]
+    async fn r2_buckets() {
+        let server = FakeServer::start().unwrap();
+        let client = client(&server);
+        let create = CreateBucket {
+            account_identifier: ACCOUNT,
+            bucket_name: "assets",
+        };
+
+        client.request(&create).await.unwrap();
+        assert_eq!(
+            error_code(client.request(&create).await.unwrap_err()),
+            (409, 10004)
+        );
+        client
+            .request(&DeleteBucket {
+                account_identifier: ACCOUNT,
+                bucket_name: "assets",
+            })
+            .await
+            .unwrap();
+        let buckets = client
+            .request(&ListBuckets {
+                account_identifier: ACCOUNT,
+            })
+            .await
+            .unwrap();
+        assert!(buckets.result.buckets.is_empty());
+    }
+
+    #[tokio::test]
+    async fn tunnels() {
+        let server = FakeServer::start().unwrap();
+        let client = client(&server);
+
+        let secret = Secret::new(vec![0; 32]);
+        let tunnel = client
+            .request(&CreateTunnel {
+                account_identifier: ACCOUNT,
+                params: create_tunnel::Params {
+                    name: "tunnel",
+                    tunnel_secret: &secret,
+                    config_src: &ConfigurationSrc::Cloudflare,
+                    metadata: None,
+                },
+            })
+            .await
+            .unwrap()
+            .result;
+
+        let deleted = client
+            .request(&DeleteTunnel {
+                account_identifier: ACCOUNT,
+                tunnel_id: &tunnel.id.to_string(),
+                params: delete_tunnel::Params { cascade: false },
+            })
+            .await
+            .unwrap()
+            .result;
+        assert!(deleted.deleted_at.is_some());
+
+        let active = client
+            .request(&ListTunnels {
+                account_identifier: ACCOUNT,
+                params: list_tunnels::Params {
+                    is_deleted: Some(false),
+                    ..Default::default()
+                },
+            })
+            .await
+            .unwrap()
+            .result;
+        assert!(active.is_empty());
+    }
+
+    #[tokio::test]
+    async fn unknown_routes_and_missing_credentials() {
+        let server = FakeServer::start().unwrap();
+        let http = reqwest::Client::new();
+
+        let response = http
+            .get(format!("{}zones", server.url()))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+
+        let response = http
+            .get(format!("{}nothing/here", server.url()))
+            .bearer_auth("test")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["success"], false);
+        assert_eq!(body["errors"][0]["code"], 7000);
+    }
+}
@@ -0,0 +1,81 @@
+use super::api::{now, ApiError, Call, Reply, Result};
+use http::StatusCode;
+use serde_json::json;
+
+/// R2 buckets of every account.
+#[derive(Default)]
+pub(super) struct Buckets(Vec<Bucket>);
+
+struct Bucket {
+    account: String,
+    name: String,
+    creation_date: String,
+}
+
+impl Buckets {
+    pub(super) fn handle(&mut self, call: &Call, account: &str, path: &[&str]) -> Result<Reply> {
+        match (call.method(), path) {
+            ("GET", []) => {
+                let buckets: Vec<_> = self
+                    .0
+                    .iter()
+                    .filter(|bucket| bucket.account == account)
+                    .map(|bucket| json!({ "name": bucket.name, "creation_date": bucket.creation_date }))
+                    .collect();
+                Ok(Reply::Json(json!({ "buckets": buckets })))
+            }
+            ("PUT", [name]) => {
+                if !is_valid_name(name) {
+                    return Err(ApiError::new(
+                        StatusCode::BAD_REQUEST,
+                        10005,
+                        "The specified bucket name is not valid.",
+                    ));
+                }
+                if self.position(account, name).is_some() {
+                    return Err(ApiError::new(
+                        StatusCode::CONFLICT,
+                        10004,
+                        "The bucket you tried to create already exists, and you own it.",
+                    ));
+                }
+                self.0.push(Bucket {
+                    account: account.to_owned(),
+                    name: name.to_string(),
+                    creation_date: now(),
+                });
+                Ok(Reply::Json(json!({})))
+            }
+            ("DELETE", [name]) => {
+                let index = self.position(account, name).ok_or_else(|| {
+                    ApiError::new(
+                        StatusCode::NOT_FOUND,
+                        10006,
+                        "The specified bucket does not exist.",
+                    )
+                })?;
+                self.0.remove(index);
+                Ok(Reply::Json(json!({})))
+            }
+            (_, [] | [_]) => call.method_not_allowed(),
+            _ => call.no_route(),
+        }
+    }
+
+    fn position(&self, account: &str, name: &str) -> Option<usize> {
+        self.0
+            .iter()
+            .position(|bucket| bucket.account == account && bucket.name == name)
+    }
+}
+
+/// Bucket names are 3 to 63 lowercase letters, digits and hyphens, which start and end with a
+/// letter or digit.
+fn is_valid_name(name: &str) -> bool {
+    (3..=63).contains(&name.len())
+        && name
+            .bytes()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == b'-')
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+}
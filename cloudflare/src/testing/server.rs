@@ -0,0 +1,109 @@
+//! A minimal HTTP/1.1 server, which answers every request on its own connection.
+use super::api::{Request, Response, State};
+use http::header::{CONTENT_LENGTH, TRANSFER_ENCODING};
+use http::StatusCode;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+pub(super) fn serve(listener: TcpListener, state: Arc<Mutex<State>>, stopped: &AtomicBool) {
+    for stream in listener.incoming() {
+        if stopped.load(Ordering::SeqCst) {
+            return;
+        }
+        let Ok(stream) = stream else { continue };
+        let state = state.clone();
+        std::thread::spawn(move || {
+            let _ = handle_connection(stream, &state);
+        });
+    }
+}
+
+fn handle_connection(stream: TcpStream, state: &Mutex<State>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let response = match read_request(&mut reader) {
+        Ok(Some(request)) => state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .handle(&request),
+        Ok(None) => return Ok(()),
+        Err(_) => http::Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(Vec::new())
+            .expect("Responses are valid"),
+    };
+    write_response(stream, response)
+}
+
+/// Reads a request, or returns `None` if the connection was closed before sending anything.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_owned());
+
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let mut request_line = line.split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        return Err(invalid("Invalid request line"));
+    };
+    let mut builder = http::Request::builder().method(method).uri(target);
+
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| invalid("Invalid header"))?;
+        builder = builder.header(name.trim(), value.trim());
+    }
+
+    let headers = builder
+        .headers_ref()
+        .ok_or_else(|| invalid("Invalid request"))?;
+    if headers.contains_key(TRANSFER_ENCODING) {
+        return Err(invalid("Chunked bodies are not supported"));
+    }
+    let length = match headers.get(CONTENT_LENGTH) {
+        Some(length) => length
+            .to_str()
+            .ok()
+            .and_then(|length| length.parse().ok())
+            .ok_or_else(|| invalid("Invalid content length"))?,
+        None => 0,
+    };
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    builder
+        .body(body)
+        .map(Some)
+        .map_err(|e| invalid(&e.to_string()))
+}
+
+fn write_response(mut stream: TcpStream, response: Response) -> io::Result<()> {
+    let status = response.status();
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        status.as_str(),
+        status.canonical_reason().unwrap_or_default()
+    );
+    for (name, value) in response.headers() {
+        head.push_str(&format!(
+            "{name}: {}\r\n",
+            String::from_utf8_lossy(value.as_bytes())
+        ));
+    }
+    head.push_str(&format!(
+        "content-length: {}\r\nconnection: close\r\n\r\n",
+        response.body().len()
+    ));
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(response.body())?;
+    stream.flush()
+}
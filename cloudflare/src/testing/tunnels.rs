@@ -0,0 +1,177 @@
+use super::api::{now, ApiError, Call, Reply, Result};
+use http::StatusCode;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+/// Cloudflare Tunnels of every account, including deleted ones.
+#[derive(Default)]
+pub(super) struct Tunnels(Vec<Tunnel>);
+
+struct Tunnel {
+    account: String,
+    id: Uuid,
+    name: String,
+    metadata: Value,
+    remote_config: bool,
+    created_at: String,
+    deleted_at: Option<String>,
+}
+
+#[serde_with::serde_as]
+#[derive(Deserialize)]
+struct CreateTunnel {
+    name: String,
+    #[serde_as(as = "serde_with::base64::Base64")]
+    tunnel_secret: Vec<u8>,
+    config_src: Option<String>,
+    metadata: Option<Value>,
+}
+
+#[serde_with::serde_as]
+#[derive(Deserialize)]
+struct UpdateTunnel {
+    name: Option<String>,
+    #[serde_as(as = "Option<serde_with::base64::Base64>")]
+    #[serde(default)]
+    tunnel_secret: Option<Vec<u8>>,
+    metadata: Option<Value>,
+}
+
+impl Tunnels {
+    pub(super) fn handle(&mut self, call: &Call, account: &str, path: &[&str]) -> Result<Reply> {
+        match (call.method(), path) {
+            ("GET", []) => self.list(call, account),
+            ("POST", []) => {
+                let params: CreateTunnel = call.json()?;
+                check_secret(&params.tunnel_secret)?;
+                self.check_name(account, &params.name)?;
+                let tunnel = Tunnel {
+                    account: account.to_owned(),
+                    id: uuid::Builder::from_random_bytes(fastrand::u128(..).to_le_bytes())
+                        .into_uuid(),
+                    name: params.name,
+                    metadata: params.metadata.unwrap_or_else(|| json!({})),
+                    remote_config: params.config_src.as_deref() == Some("cloudflare"),
+                    created_at: now(),
+                    deleted_at: None,
+                };
+                let json = tunnel.to_json();
+                self.0.push(tunnel);
+                Ok(Reply::Json(json))
+            }
+            ("GET", [id]) => Ok(Reply::Json(self.get(account, id)?.to_json())),
+            ("PATCH", [id]) => {
+                let params: UpdateTunnel = call.json()?;
+                if let Some(secret) = &params.tunnel_secret {
+                    check_secret(secret)?;
+                }
+                if let Some(name) = &params.name {
+                    if self.get(account, id)?.name != *name {
+                        self.check_name(account, name)?;
+                    }
+                }
+                let tunnel = self.get(account, id)?;
+                if let Some(name) = params.name {
+                    tunnel.name = name;
+                }
+                if let Some(metadata) = params.metadata {
+                    tunnel.metadata = metadata;
+                }
+                Ok(Reply::Json(tunnel.to_json()))
+            }
+            ("DELETE", [id]) => {
+                let tunnel = self.get(account, id)?;
+                tunnel.deleted_at = Some(now());
+                Ok(Reply::Json(tunnel.to_json()))
+            }
+            (_, [] | [_]) => call.method_not_allowed(),
+            _ => call.no_route(),
+        }
+    }
+
+    fn list(&self, call: &Call, account: &str) -> Result<Reply> {
+        let query = &call.query;
+        let is_deleted = query.parse_value::<bool>("is_deleted")?;
+        let tunnels = self
+            .0
+            .iter()
+            .filter(|tunnel| tunnel.account == account)
+            .filter(|tunnel| {
+                is_deleted.is_none_or(|deleted| tunnel.deleted_at.is_some() == deleted)
+            })
+            .filter(|tunnel| query.get("name").is_none_or(|name| tunnel.name == name))
+            .filter(|tunnel| {
+                query
+                    .get("uuid")
+                    .is_none_or(|id| tunnel.id.to_string() == id)
+            })
+            .filter(|tunnel| {
+                query
+                    .get("include_prefix")
+                    .is_none_or(|prefix| tunnel.name.starts_with(prefix))
+            })
+            .filter(|tunnel| {
+                query
+                    .get("exclude_prefix")
+                    .is_none_or(|prefix| !tunnel.name.starts_with(prefix))
+            })
+            .map(Tunnel::to_json)
+            .collect();
+        query.paginate(tunnels, 20)
+    }
+
+    /// Returns a tunnel which has not been deleted.
+    fn get(&mut self, account: &str, id: &str) -> Result<&mut Tunnel> {
+        self.0
+            .iter_mut()
+            .find(|tunnel| {
+                tunnel.account == account
+                    && tunnel.id.to_string() == id
+                    && tunnel.deleted_at.is_none()
+            })
+            .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, 1003, "Tunnel not found"))
+    }
+
+    fn check_name(&self, account: &str, name: &str) -> Result<()> {
+        let exists = self.0.iter().any(|tunnel| {
+            tunnel.account == account && tunnel.name == name && tunnel.deleted_at.is_none()
+        });
+        if exists {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                1013,
+                "You already have a tunnel with this name.",
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn check_secret(secret: &[u8]) -> Result<()> {
+    if secret.len() < 32 {
+        return Err(ApiError::bad_request(
+            "tunnel_secret must be at least 32 bytes long",
+        ));
+    }
+    Ok(())
+}
+
+impl Tunnel {
+    fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "account_tag": self.account,
+            "created_at": self.created_at,
+            "deleted_at": self.deleted_at,
+            "name": self.name,
+            "connections": [],
+            "conns_active_at": null,
+            "conns_inactive_at": null,
+            "tun_type": "cfd_tunnel",
+            "metadata": self.metadata,
+            "status": "inactive",
+            "remote_config": self.remote_config,
+        })
+    }
+}
@@ -0,0 +1,130 @@
+use super::api::{new_id, now, ApiError, Call, Reply, Result};
+use http::StatusCode;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Default)]
+pub(super) struct Zones(Vec<Zone>);
+
+#[derive(Clone)]
+pub(super) struct Zone {
+    pub(super) id: String,
+    pub(super) name: String,
+    account_id: String,
+    zone_type: String,
+    created_on: String,
+}
+
+#[derive(Deserialize)]
+struct CreateZone {
+    name: String,
+    account: Account,
+    #[serde(rename = "type")]
+    zone_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Account {
+    id: Option<String>,
+}
+
+impl Zones {
+    pub(super) fn handle(&mut self, call: &Call, path: &[&str]) -> Result<Reply> {
+        match (call.method(), path) {
+            ("GET", []) => self.list(call),
+            ("POST", []) => self.create(call),
+            ("GET", [id]) => Ok(Reply::Json(self.get(id)?.to_json())),
+            ("DELETE", [id]) => {
+                self.get(id)?;
+                self.0.retain(|zone| zone.id != *id);
+                Ok(Reply::Json(json!({ "id": id })))
+            }
+            ("PUT", [id, "activation_check"]) => {
+                self.get(id)?;
+                Ok(Reply::Json(json!({ "id": id })))
+            }
+            (_, [] | [_] | [_, "activation_check"]) => call.method_not_allowed(),
+            _ => call.no_route(),
+        }
+    }
+
+    pub(super) fn get(&self, id: &str) -> Result<&Zone> {
+        self.0.iter().find(|zone| zone.id == id).ok_or_else(|| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                7003,
+                format!(
+                    "Could not route to /zones/{id}, perhaps your object identifier is invalid?"
+                ),
+            )
+        })
+    }
+
+    fn list(&self, call: &Call) -> Result<Reply> {
+        let zones = self
+            .0
+            .iter()
+            .filter(|zone| call.query.get("name").is_none_or(|name| zone.name == name))
+            // Zones are active as soon as they are created.
+            .filter(|_| {
+                call.query
+                    .get("status")
+                    .is_none_or(|status| status == "active")
+            })
+            .map(Zone::to_json)
+            .collect();
+        call.query.paginate(zones, 20)
+    }
+
+    fn create(&mut self, call: &Call) -> Result<Reply> {
+        let params: CreateZone = call.json()?;
+        if self.0.iter().any(|zone| zone.name == params.name) {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                1061,
+                format!("{} already exists", params.name),
+            ));
+        }
+        let Some(account_id) = params.account.id else {
+            return Err(ApiError::bad_request("account.id is required"));
+        };
+        let zone = Zone {
+            id: new_id(),
+            name: params.name,
+            account_id,
+            zone_type: params.zone_type.unwrap_or_else(|| "full".to_owned()),
+            created_on: now(),
+        };
+        let json = zone.to_json();
+        self.0.push(zone);
+        Ok(Reply::Json(json))
+    }
+}
+
+impl Zone {
+    fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "name": self.name,
+            "account": { "id": self.account_id, "name": "Fake account" },
+            "activated_on": self.created_on,
+            "created_on": self.created_on,
+            "modified_on": self.created_on,
+            "development_mode": 0,
+            "meta": {
+                "custom_certificate_quota": 1,
+                "page_rule_quota": 3,
+                "phishing_detected": false,
+            },
+            "name_servers": ["ns1.example.com", "ns2.example.com"],
+            "original_dnshost": null,
+            "original_name_servers": null,
+            "original_registrar": null,
+            "owner": { "type": "user", "id": null, "email": null },
+            "paused": false,
+            "permissions": ["#zone:read", "#zone:edit"],
+            "status": "active",
+            "type": self.zone_type,
+        })
+    }
+}